@@ -0,0 +1,179 @@
+use std::fmt::{self, Display, Formatter};
+use crate::{Instruction, Number};
+
+/*
+Instructions can be printed in two syntaxes:
+
+    Spec:   mnemonics and operand order as described in arch-spec (`add r0 r1 4`)
+    Pseudo: an expression-like representation (`r0 = r1 + 4`)
+
+`Display` for `Instruction` uses the spec syntax, and the alternate flag (`{:#}`) selects the pseudo syntax.
+A `Listing` formats whole lines of a memory image, optionally preceded by the raw words in hex and decimal.
+*/
+
+const MAX_INSTRUCTION_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    #[default]
+    Spec,
+    Pseudo
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Number::Literal(x)  => write!(f, "{}", x),
+            Number::Register(x) => write!(f, "r{}", x)
+        }
+    }
+}
+
+struct Char<'a>(&'a Number);
+
+impl Display for Char<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.0 {
+            Number::Literal(x @ 32 ..= 126) => write!(f, "'{}'", *x as u8 as char),
+            number                          => write!(f, "{}", number)
+        }
+    }
+}
+
+impl Instruction {
+    fn fmt_spec(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Instruction::Add(a, b, c)                => write!(f, "add r{} {} {}", a, b, c),
+            Instruction::BitwiseAnd(a, b, c)         => write!(f, "and r{} {} {}", a, b, c),
+            Instruction::BitwiseNot(a, b)            => write!(f, "not r{} {}", a, b),
+            Instruction::BitwiseOr(a, b, c)          => write!(f, "or r{} {} {}", a, b, c),
+            Instruction::CompareEquals(a, b, c)      => write!(f, "eq r{} {} {}", a, b, c),
+            Instruction::CompareGreaterThan(a, b, c) => write!(f, "gt r{} {} {}", a, b, c),
+            Instruction::FunctionCall(a)             => write!(f, "call {}", a),
+            Instruction::FunctionReturn              => write!(f, "ret"),
+            Instruction::Halt                        => write!(f, "halt"),
+            Instruction::Jump(a)                     => write!(f, "jmp {}", a),
+            Instruction::JumpIfFalse(a, b)           => write!(f, "jf {} {}", a, b),
+            Instruction::JumpIfTrue(a, b)            => write!(f, "jt {} {}", a, b),
+            Instruction::MemoryRead(a, b)            => write!(f, "rmem r{} {}", a, b),
+            Instruction::MemoryWrite(a, b)           => write!(f, "wmem {} {}", a, b),
+            Instruction::Mod(a, b, c)                => write!(f, "mod r{} {} {}", a, b, c),
+            Instruction::Multiply(a, b, c)           => write!(f, "mult r{} {} {}", a, b, c),
+            Instruction::NoOp                        => write!(f, "noop"),
+            Instruction::Pop(a)                      => write!(f, "pop r{}", a),
+            Instruction::PrintChar(a)                => write!(f, "out {}", Char(a)),
+            Instruction::Push(a)                     => write!(f, "push {}", a),
+            Instruction::ReadChar(a)                 => write!(f, "in r{}", a),
+            Instruction::SetRegister(a, b)           => write!(f, "set r{} {}", a, b),
+            Instruction::Unknown(opcode)             => write!(f, "unknown {}", opcode)
+        }
+    }
+
+    fn fmt_pseudo(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Instruction::Add(a, b, c)                => write!(f, "r{} = {} + {}", a, b, c),
+            Instruction::BitwiseAnd(a, b, c)         => write!(f, "r{} = {} & {}", a, b, c),
+            Instruction::BitwiseNot(a, b)            => write!(f, "r{} = not {}", a, b),
+            Instruction::BitwiseOr(a, b, c)          => write!(f, "r{} = {} | {}", a, b, c),
+            Instruction::CompareEquals(a, b, c)      => write!(f, "r{} = {} == {}", a, b, c),
+            Instruction::CompareGreaterThan(a, b, c) => write!(f, "r{} = {} > {}", a, b, c),
+            Instruction::FunctionCall(a)             => write!(f, "call {}", a),
+            Instruction::FunctionReturn              => write!(f, "ret"),
+            Instruction::Halt                        => write!(f, "halt"),
+            Instruction::Jump(a)                     => write!(f, "jmp {}", a),
+            Instruction::JumpIfFalse(a, b)           => write!(f, "jmp {} if not {}", b, a),
+            Instruction::JumpIfTrue(a, b)            => write!(f, "jmp {} if {}", b, a),
+            Instruction::MemoryRead(a, b)            => write!(f, "r{} = m[{}]", a, b),
+            Instruction::MemoryWrite(a, b)           => write!(f, "m[{}] = {}", a, b),
+            Instruction::Mod(a, b, c)                => write!(f, "r{} = {} % {}", a, b, c),
+            Instruction::Multiply(a, b, c)           => write!(f, "r{} = {} * {}", a, b, c),
+            Instruction::NoOp                        => write!(f, "noop"),
+            Instruction::Pop(a)                      => write!(f, "pop into r{}", a),
+            Instruction::PrintChar(a)                => write!(f, "write {}", Char(a)),
+            Instruction::Push(a)                     => write!(f, "push {}", a),
+            Instruction::ReadChar(a)                 => write!(f, "read into r{}", a),
+            Instruction::SetRegister(a, b)           => write!(f, "r{} = {}", a, b),
+            Instruction::Unknown(opcode)             => write!(f, "unknown opcode {}", opcode)
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if f.alternate() {
+            self.fmt_pseudo(f)
+        } else {
+            self.fmt_spec(f)
+        }
+    }
+}
+
+pub struct Line<'a> {
+    pub address: u16,
+    pub instruction: Instruction,
+    words: &'a [u16],
+    syntax: Syntax,
+    raw_words: bool
+}
+
+impl Display for Line<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.address)?;
+
+        if self.raw_words {
+            let hex = self.words.iter().map(|w| format!("{:04x}", w)).collect::<Vec<String>>().join(" ");
+            let dec = self.words.iter().map(|w| w.to_string()).collect::<Vec<String>>().join(" ");
+
+            write!(f, "{:<hex_width$}  {:<dec_width$}  ", hex, dec,
+                hex_width = 5 * MAX_INSTRUCTION_SIZE - 1, dec_width = 6 * MAX_INSTRUCTION_SIZE - 1)?;
+        }
+
+        match self.syntax {
+            Syntax::Spec   => write!(f, "{}", self.instruction),
+            Syntax::Pseudo => write!(f, "{:#}", self.instruction)
+        }
+    }
+}
+
+pub struct Listing<'a> {
+    memory: &'a [u16],
+    syntax: Syntax,
+    raw_words: bool
+}
+
+impl<'a> Listing<'a> {
+    pub fn new(memory: &'a [u16]) -> Self {
+        Self { memory, syntax: Syntax::default(), raw_words: false }
+    }
+
+    pub fn syntax(self, syntax: Syntax) -> Self {
+        Self { syntax, ..self }
+    }
+
+    pub fn raw_words(self, raw_words: bool) -> Self {
+        Self { raw_words, ..self }
+    }
+
+    pub fn lines_from(&self, address: u16) -> impl Iterator<Item = Line<'a>> + '_ {
+        let mut next_address = address as usize;
+
+        std::iter::from_fn(move || {
+            if next_address >= self.memory.len() {
+                return None;
+            }
+
+            let instruction = Instruction::decode(self.memory, next_address as u16);
+            let end = (next_address + instruction.size() as usize).min(self.memory.len());
+            let line = Line {
+                address: next_address as u16,
+                instruction,
+                words: &self.memory[next_address .. end],
+                syntax: self.syntax,
+                raw_words: self.raw_words
+            };
+
+            next_address = end;
+            Some(line)
+        })
+    }
+}
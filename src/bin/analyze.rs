@@ -0,0 +1,80 @@
+use std::{env, process};
+use synacor_vm::{
+    callgraph::trace_calls,
+    cli::{binary_argument, load},
+    dataflow::{Liveness, Program, ReachingDefinitions, Values},
+    ir::Function,
+    ssa::SsaFunction,
+    Instruction
+};
+
+const ENTRY_POINT: u16 = 0;
+
+fn print_function(program: &Program, function: &Function) {
+    let values = Values::compute(function, &program.summaries);
+    let liveness = Liveness::compute(function, &program.summaries, &values.saves);
+    let reaching = ReachingDefinitions::compute(function, &program.summaries);
+
+    println!("{}", SsaFunction::build(function, &program.summaries));
+
+    for block in function.blocks.keys() {
+        let live_in: Vec<String> = liveness.live_in[block].iter().map(|r| format!("r{}", r)).collect();
+        let live_out: Vec<String> = liveness.live_out[block].iter().map(|r| format!("r{}", r)).collect();
+        let reaching_in: Vec<String> = reaching.reach_in[block].iter().map(|def| def.to_string()).collect();
+
+        println!("block {}", block);
+        println!("    live in:   {}", live_in.join(" "));
+        println!("    live out:  {}", live_out.join(" "));
+        println!("    reaching:  {}", reaching_in.join(" "));
+    }
+
+    println!();
+    println!("summary: {}", program.summaries.functions[&function.entry]);
+}
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn main() {
+    let (binary, args) = binary_argument(env::args().skip(1).collect()).unwrap_or_else(|error| exit_with(&error));
+    let mut vm = load(&binary).unwrap_or_else(|error| exit_with(&error));
+
+    vm.dbg_set_output_enabled(false);
+
+    // Most of the code is decrypted at startup, so it's lifted once the game waits for the first command, along with the
+    // targets of the indirect calls made so far
+    let traced = trace_calls(&mut vm, &[]);
+    let mut roots: Vec<u16> = [ENTRY_POINT].into_iter().chain(traced.values().flatten().copied()).collect();
+
+    match args.first() {
+        Some(address) => {
+            let address: u16 = address
+                .parse()
+                .ok()
+                .filter(|&address| (address as usize) < vm.dbg_get_memory().len())
+                .unwrap_or_else(|| exit_with(&format!("{} should be a function address", address)));
+
+            if let Instruction::Unknown(_) = Instruction::decode(vm.dbg_get_memory(), address) {
+                exit_with(&format!("{} doesn't start an instruction once the game has started", address));
+            }
+
+            roots.push(address);
+
+            let program = Program::lift_with_traces(vm.dbg_get_memory(), &roots, &traced);
+
+            match program.functions.get(&address) {
+                Some(function) => print_function(&program, function),
+                None           => exit_with(&format!("{} couldn't be lifted as a function", address))
+            }
+        }
+        None => {
+            let program = Program::lift_with_traces(vm.dbg_get_memory(), &roots, &traced);
+
+            for (entry, summary) in &program.summaries.functions {
+                println!("{}: {}", entry, summary);
+            }
+        }
+    }
+}
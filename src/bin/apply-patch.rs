@@ -0,0 +1,31 @@
+use std::{env, process};
+use synacor_vm::patch::{read_image, write_image, Patch};
+
+const USAGE: &str = "usage: apply-patch <patch> <binary> [<patched binary>]";
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+// Without an output binary, only checks that the patch applies to the binary
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (patch, binary, output) = match &args[..] {
+        [patch, binary]         => (patch, binary, None),
+        [patch, binary, output] => (patch, binary, Some(output)),
+        _                       => exit_with(USAGE)
+    };
+    let patch = Patch::read(patch).unwrap_or_else(|error| exit_with(&error));
+    let mut image = read_image(binary).unwrap_or_else(|error| exit_with(&error));
+
+    patch.apply(&mut image).unwrap_or_else(|error| exit_with(&error));
+
+    match output {
+        Some(output) => {
+            write_image(output, &image).unwrap_or_else(|error| exit_with(&error));
+            println!("{} words changed, patched binary written to {}", patch.changes.len(), output);
+        }
+        None => println!("the patch applies to {} ({} words changed)", binary, patch.changes.len())
+    }
+}
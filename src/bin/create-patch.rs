@@ -0,0 +1,29 @@
+use std::{env, fs, process};
+use synacor_vm::patch::{read_image, Patch};
+
+const USAGE: &str = "usage: create-patch <original binary> <patched binary> [<patch>]";
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (original, patched, output) = match &args[..] {
+        [original, patched]         => (original, patched, None),
+        [original, patched, output] => (original, patched, Some(output)),
+        _                           => exit_with(USAGE)
+    };
+    let original = read_image(original).unwrap_or_else(|error| exit_with(&error));
+    let patched = read_image(patched).unwrap_or_else(|error| exit_with(&error));
+    let patch = Patch::diff(&original, &patched);
+
+    match output {
+        Some(output) => {
+            fs::write(output, patch.to_string()).unwrap_or_else(|_| exit_with(&format!("Unable to write {}", output)));
+            println!("{} words changed, patch written to {}", patch.changes.len(), output);
+        }
+        None => print!("{}", patch)
+    }
+}
@@ -0,0 +1,64 @@
+use std::{collections::BTreeSet, env, process};
+use synacor_vm::{cli::{binary_argument, load}, discovery::discover, explorer::Explorer};
+
+const DEFAULT_BUDGET: usize = 1000;
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn main() {
+    let (binary, args) = binary_argument(env::args().skip(1).collect()).unwrap_or_else(|error| exit_with(&error));
+    let budget = args
+        .iter()
+        .skip_while(|arg| *arg != "--budget")
+        .nth(1)
+        .map(|budget| budget.parse().unwrap_or_else(|_| exit_with(&format!("{} should be a number of states", budget))))
+        .unwrap_or(DEFAULT_BUDGET);
+    let mut vm = load(&binary).unwrap_or_else(|error| exit_with(&error));
+
+    vm.dbg_set_output_enabled(false);
+
+    let layout = discover(&mut vm);
+    let world = layout.read_world(vm.dbg_get_memory());
+    let exploration = Explorer::new(layout.current_location).budget(budget).explore(vm);
+    let name = |location: usize| world.location(location).map_or("?", |location| location.name.as_str());
+
+    for state in &exploration.states {
+        let halted = if state.halted { "  halted" } else { "" };
+
+        println!("state {} [{}] {}{}", state.id, state.location, name(state.location), halted);
+
+        for transition in exploration.transitions.iter().filter(|transition| transition.from == state.id) {
+            match transition.to {
+                Some(to) => println!("    {} -> state {}", transition.command, to),
+                None     => println!("    {} -> unexplored", transition.command)
+            }
+        }
+    }
+
+    println!();
+
+    let mut code_connections = BTreeSet::new();
+
+    for transition in &exploration.transitions {
+        let Some(to) = transition.to else { continue };
+        let (from, to) = (exploration.states[transition.from].location, exploration.states[to].location);
+        let static_exit = world.location(from).is_some_and(|location| location.connections
+            .iter()
+            .any(|exit| !exit.synthetic && exit.target == to));
+
+        if from != to && !static_exit && code_connections.insert((from, to, &transition.command)) {
+            println!("code connection: [{}] {} -> [{}] {} ({})", from, name(from), to, name(to), transition.command);
+        }
+    }
+
+    for (state, code) in &exploration.codes {
+        println!("code found from state {}: {}", state, code);
+    }
+
+    if !exploration.complete {
+        println!("state budget of {} reached, the exploration is incomplete", budget);
+    }
+}
@@ -0,0 +1,42 @@
+use std::{env, fs, process};
+use synacor_vm::{cli::{binary_argument, load}, codes::{Codes, ANNOUNCEMENTS}, replay::replay};
+
+const ARCH_SPEC: &str = "files/arch-spec.txt";
+const INPUTS: &str = "files/inputs";
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn main() {
+    let (binary, mut scripts) = binary_argument(env::args().skip(1).collect()).unwrap_or_else(|error| exit_with(&error));
+
+    if scripts.is_empty() {
+        scripts = fs::read_dir(INPUTS)
+            .unwrap_or_else(|_| exit_with(&format!("Unable to read {}", INPUTS)))
+            .map(|entry| entry.unwrap().path().display().to_string())
+            .collect();
+        scripts.sort();
+    }
+
+    let mut codes = Codes::default();
+
+    codes.watch(&fs::read_to_string(ARCH_SPEC).unwrap_or_else(|_| exit_with(&format!("Unable to read {}", ARCH_SPEC))));
+
+    for script in &scripts {
+        let vm = load(&binary).unwrap_or_else(|error| exit_with(&error));
+        let input = fs::read_to_string(script).unwrap_or_else(|_| exit_with(&format!("Unable to read {}", script)));
+
+        codes.watch(&replay(vm, &input));
+    }
+
+    print!("{}", codes.to_table());
+
+    let missing = (1 ..= ANNOUNCEMENTS.len()).filter(|&number| !codes.found.iter().any(|code| code.number == number)).count();
+
+    if missing > 0 {
+        eprintln!("{} of {} codes not found", missing, ANNOUNCEMENTS.len());
+        process::exit(1);
+    }
+}
@@ -0,0 +1,47 @@
+use std::{env, fs, process};
+use synacor_vm::{callgraph::{trace_calls, CallGraph}, cli::{binary_argument, load}, dataflow::Program};
+
+const ENTRY_POINT: u16 = 0;
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn main() {
+    let (binary, args) = binary_argument(env::args().skip(1).collect()).unwrap_or_else(|error| exit_with(&error));
+    let mut dot = false;
+    let mut commands = vec![];
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dot"   => dot = true,
+            "--trace" => {
+                let path = args.next().unwrap_or_else(|| exit_with("--trace should be followed by an input script"));
+
+                commands.extend(fs::read_to_string(&path)
+                    .unwrap_or_else(|_| exit_with(&format!("{} should be a readable file", path)))
+                    .lines()
+                    .filter(|line| !line.starts_with('$'))
+                    .map(|line| line.to_string()));
+            }
+            _ => exit_with(&format!("Unknown argument {}, expected --binary <file>, --dot or --trace <script>", arg))
+        }
+    }
+
+    let mut vm = load(&binary).unwrap_or_else(|error| exit_with(&error));
+
+    vm.dbg_set_output_enabled(false);
+
+    let traced = trace_calls(&mut vm, &commands);
+    let roots: Vec<u16> = [ENTRY_POINT].into_iter().chain(traced.values().flatten().copied()).collect();
+    let program = Program::lift_with_traces(vm.dbg_get_memory(), &roots, &traced);
+    let graph = CallGraph::build(&program);
+
+    if dot {
+        print!("{}", graph.to_dot());
+    } else {
+        print!("{}", graph.to_summary());
+    }
+}
@@ -0,0 +1,72 @@
+use std::{env, process};
+use synacor_vm::{
+    cli::{binary_argument, load},
+    discovery::discover,
+    patch::{read_image, write_image, Patch},
+    teleporter::{bypass, verify_bypass}
+};
+
+const USAGE: &str = "usage: patch-teleporter [--binary <file>] <r7> [--output <patched binary>]";
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn main() {
+    let (binary, args) = binary_argument(env::args().skip(1).collect()).unwrap_or_else(|error| exit_with(&error));
+    let (r7, output) = match &args[..] {
+        [r7]                                     => (r7, None),
+        [r7, flag, output] if flag == "--output" => (r7, Some(output)),
+        _                                        => exit_with(USAGE)
+    };
+    let r7: u16 = r7.parse().unwrap_or_else(|_| exit_with(USAGE));
+    let mut vm = load(&binary).unwrap_or_else(|error| exit_with(&error));
+
+    vm.dbg_set_output_enabled(false);
+
+    let layout = discover(&mut vm);
+    let patch = bypass(vm.dbg_get_memory(), &layout.teleporter, r7).unwrap_or_else(|error| exit_with(&error));
+
+    println!("changed words:");
+
+    for change in &patch.changes {
+        println!("    {}", change);
+    }
+
+    println!();
+
+    // The patched binary is verified on its own, otherwise the changes are applied on the VM that's already running
+    let verification = match output {
+        Some(output) => {
+            let mut image = read_image(&binary).unwrap_or_else(|error| exit_with(&error));
+
+            patch.apply(&mut image).unwrap_or_else(|error| exit_with(&error));
+            write_image(output, &image).unwrap_or_else(|error| exit_with(&error));
+            println!("patched binary written to {}", output);
+
+            let mut patched = load(output).unwrap_or_else(|error| exit_with(&error));
+
+            patched.dbg_set_output_enabled(false);
+            patched.run();
+            verify_bypass(&patched, &layout, &Patch::default())
+        }
+        None => {
+            for command in patch.to_commands() {
+                println!("{}", command);
+            }
+
+            verify_bypass(&vm, &layout, &patch)
+        }
+    };
+
+    match verification {
+        Ok(codes) => {
+            let codes: Vec<String> = codes.iter().map(|code| format!("{} code {}", code.source, code.value)).collect();
+
+            println!();
+            println!("verified: the teleporter takes the player to the beach ({})", codes.join(", "));
+        }
+        Err(error) => exit_with(&format!("verification failed: {}", error))
+    }
+}
@@ -0,0 +1,57 @@
+use std::{env, process};
+use synacor_vm::{
+    cli::{binary_argument, load},
+    discovery::discover,
+    route::{resolve, to_script, Step, Target},
+    world::World
+};
+
+const USAGE: &str = "usage: plan-route [--binary <file>] [--from <location>] [--take <item> | --use <item> | --go <location>]... <location or item>";
+
+fn exit_with(error: &str) -> ! {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn resolve_or_exit(world: &World, name: Option<String>) -> Target {
+    let name = name.unwrap_or_else(|| exit_with(USAGE));
+
+    resolve(world, &name).unwrap_or_else(|error| exit_with(&error))
+}
+
+fn main() {
+    let (binary, args) = binary_argument(env::args().skip(1).collect()).unwrap_or_else(|error| exit_with(&error));
+    let mut vm = load(&binary).unwrap_or_else(|error| exit_with(&error));
+
+    vm.dbg_set_output_enabled(false);
+
+    let layout = discover(&mut vm);
+    let world = layout.read_world(vm.dbg_get_memory());
+    let mut from = layout.start_location;
+    let mut steps = vec![];
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, name) = if arg.starts_with("--") { (arg.as_str(), args.next()) } else { ("", Some(arg.clone())) };
+        let step = match (flag, resolve_or_exit(&world, name)) {
+            ("--from", Target::Location(id))    => { from = id; continue }
+            ("--go" | "", Target::Location(id)) => Step::Go(id),
+            ("--take" | "", Target::Item(id))   => Step::Take(id),
+            ("--use", Target::Item(id))         => Step::Use(id),
+            _                                   => exit_with(USAGE)
+        };
+
+        steps.push(step);
+    }
+
+    if steps.is_empty() {
+        exit_with(USAGE);
+    }
+
+    let planner = layout.planner(vm.dbg_get_memory(), &world);
+
+    match planner.plan(from, &steps) {
+        Ok(commands) => print!("{}", to_script(&commands)),
+        Err(error)   => exit_with(&error)
+    }
+}
@@ -0,0 +1,4 @@
+// Same as `synacor replay`, see cli.rs
+fn main() {
+    synacor_vm::cli::main(&["replay"]);
+}
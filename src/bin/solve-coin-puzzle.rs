@@ -0,0 +1,4 @@
+// Same as `synacor solve coins`, see cli.rs
+fn main() {
+    synacor_vm::cli::main(&["solve", "coins"]);
+}
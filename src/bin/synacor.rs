@@ -0,0 +1,4 @@
+// Multi-tool with the tools as subcommands, see cli.rs
+fn main() {
+    synacor_vm::cli::main(&[]);
+}
@@ -0,0 +1,205 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt::Write};
+use crate::{dataflow::{CallTargets, Program}, ir::literal, VM};
+
+/*
+The call graph has a node for each function found by the program discovery and an edge for each call site and target.
+Targets of register-indirect calls come from constant propagation and from traces of the VM, and the ones which couldn't
+be resolved are reported separately.
+
+Recursion is found through the strongly connected components of the graph (Tarjan), any component with more than one
+function or with a function calling itself being a recursive group. Every function of a group can reach the others
+through calls, although not necessarily in the order of their addresses, so the group is reported as a set.
+*/
+
+pub struct CallSite {
+    pub address: u16,
+    pub callers: BTreeSet<u16>,
+    pub targets: BTreeSet<u16>,
+    pub indirect: bool
+}
+
+pub struct CallGraph {
+    pub functions: BTreeSet<u16>,
+    pub sites: Vec<CallSite>,
+    pub cycles: Vec<BTreeSet<u16>>
+}
+
+impl CallGraph {
+    pub fn build(program: &Program) -> Self {
+        let mut sites: BTreeMap<u16, CallSite> = BTreeMap::new();
+
+        for (entry, function) in &program.functions {
+            for (address, target) in function.call_sites() {
+                let site = sites.entry(address).or_insert_with(|| CallSite {
+                    address,
+                    callers: BTreeSet::new(),
+                    targets: match literal(&target) {
+                        Some(target) => BTreeSet::from([target]),
+                        None         => program.summaries.call_targets.get(&address).cloned().unwrap_or_default()
+                    },
+                    indirect: literal(&target).is_none()
+                });
+
+                site.callers.insert(*entry);
+            }
+        }
+
+        let mut graph = Self { functions: program.functions.keys().copied().collect(), sites: sites.into_values().collect(), cycles: vec![] };
+
+        graph.cycles = graph.find_cycles();
+        graph
+    }
+
+    pub fn edges(&self) -> BTreeSet<(u16, u16)> {
+        self.sites
+            .iter()
+            .flat_map(|site| site.callers.iter().flat_map(|&caller| site.targets.iter().map(move |&target| (caller, target))))
+            .collect()
+    }
+
+    pub fn is_recursive(&self, function: u16) -> bool {
+        self.cycles.iter().any(|cycle| cycle.contains(&function))
+    }
+
+    pub fn unresolved_sites(&self) -> impl Iterator<Item = &CallSite> {
+        self.sites.iter().filter(|site| site.targets.is_empty())
+    }
+
+    fn find_cycles(&self) -> Vec<BTreeSet<u16>> {
+        let edges = self.edges();
+        let mut successors: BTreeMap<u16, Vec<u16>> = BTreeMap::new();
+
+        for &(caller, callee) in &edges {
+            successors.entry(caller).or_default().push(callee);
+        }
+
+        let mut tarjan = Tarjan { successors: &successors, index: BTreeMap::new(), low: BTreeMap::new(), stack: vec![], components: vec![] };
+
+        for &function in &self.functions {
+            if !tarjan.index.contains_key(&function) {
+                tarjan.visit(function);
+            }
+        }
+
+        tarjan.components
+            .into_iter()
+            .filter(|component| component.len() > 1 || component.iter().any(|&f| edges.contains(&(f, f))))
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph G {\n");
+
+        for &function in &self.functions {
+            let fill = if self.is_recursive(function) { r#", fillcolor="lightsalmon", style="filled""# } else { "" };
+
+            writeln!(dot, r#"    {id} [label="{id}"{fill}]"#, id = function, fill = fill).unwrap();
+        }
+
+        for site in &self.sites {
+            for &caller in &site.callers {
+                for &target in &site.targets {
+                    let recursive = self.cycles.iter().any(|cycle| cycle.contains(&caller) && cycle.contains(&target));
+                    let color = if recursive { r#", color="red""# } else { "" };
+                    let style = if site.indirect { r#", style="dashed""# } else { "" };
+
+                    writeln!(dot, r#"    {} -> {} [label="{}"{}{}]"#, caller, target, site.address, color, style).unwrap();
+                }
+
+                if site.targets.is_empty() {
+                    writeln!(dot, r#"    {} -> unresolved [label="{}", style="dashed"]"#, caller, site.address).unwrap();
+                }
+            }
+        }
+
+        if self.unresolved_sites().next().is_some() {
+            dot.push_str("    unresolved [label=\"?\", shape=\"box\"]\n");
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_summary(&self) -> String {
+        let edges = self.edges();
+        let mut summary = String::new();
+
+        writeln!(summary, "{:>8} {:>7} {:>8}", "function", "fan-in", "fan-out").unwrap();
+
+        for &function in &self.functions {
+            let fan_in = edges.iter().filter(|(_, callee)| *callee == function).count();
+            let fan_out = edges.iter().filter(|(caller, _)| *caller == function).count();
+            let recursive = if self.is_recursive(function) { "  recursive" } else { "" };
+
+            writeln!(summary, "{:>8} {:>7} {:>8}{}", function, fan_in, fan_out, recursive).unwrap();
+        }
+
+        for cycle in &self.cycles {
+            let cycle: Vec<String> = cycle.iter().map(|f| f.to_string()).collect();
+
+            writeln!(summary, "\nrecursive group: {{{}}}", cycle.join(", ")).unwrap();
+        }
+
+        for site in self.sites.iter().filter(|site| site.indirect) {
+            let targets: Vec<String> = site.targets.iter().map(|f| f.to_string()).collect();
+            let targets = if targets.is_empty() { "unresolved".to_string() } else { targets.join(", ") };
+
+            writeln!(summary, "\nindirect call at {}: {}", site.address, targets).unwrap();
+        }
+
+        summary
+    }
+}
+
+struct Tarjan<'a> {
+    successors: &'a BTreeMap<u16, Vec<u16>>,
+    index: BTreeMap<u16, usize>,
+    low: BTreeMap<u16, usize>,
+    stack: Vec<u16>,
+    components: Vec<BTreeSet<u16>>
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, function: u16) {
+        let index = self.index.len();
+
+        self.index.insert(function, index);
+        self.low.insert(function, index);
+        self.stack.push(function);
+
+        for &callee in self.successors.get(&function).into_iter().flatten() {
+            if !self.index.contains_key(&callee) {
+                self.visit(callee);
+                self.low.insert(function, self.low[&function].min(self.low[&callee]));
+            } else if self.stack.contains(&callee) {
+                self.low.insert(function, self.low[&function].min(self.index[&callee]));
+            }
+        }
+
+        if self.low[&function] == index {
+            let position = self.stack.iter().position(|&f| f == function).unwrap();
+
+            self.components.push(self.stack.split_off(position).into_iter().collect());
+        }
+    }
+}
+
+// Runs the given game commands, returning the traced call targets of each call site
+pub fn trace_calls(vm: &mut VM, commands: &[String]) -> CallTargets {
+    let mut call_targets = CallTargets::new();
+
+    vm.dbg_set_call_tracing(true);
+    vm.run();
+
+    for command in commands {
+        vm.input_command(&format!("{}\n", command));
+        vm.run();
+    }
+
+    for &(site, target) in vm.dbg_get_call_trace().into_iter().flatten() {
+        call_targets.entry(site).or_default().insert(target);
+    }
+
+    vm.dbg_set_call_tracing(false);
+    call_targets
+}
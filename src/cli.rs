@@ -0,0 +1,223 @@
+mod debug;
+mod disasm;
+mod dump_image;
+mod graph;
+mod replay;
+mod run;
+mod solve;
+
+use std::{collections::HashMap, env, fs, process, str::FromStr};
+use crate::{patch::read_image, VM};
+
+/*
+The `synacor` binary gathers the tools as subcommands:
+
+    synacor run                 runs the binary reading the game commands from the input script and then stdin
+    synacor debug               runs the binary through the debugger
+    synacor disasm              disassembles the binary
+    synacor graph               exports the locations of the game world and their connections
+    synacor solve teleporter    solves the teleporter check
+    synacor solve vault         solves the grid of rooms opening the vault
+    synacor solve coins         solves the equation of the monument
+    synacor replay              replays an input script headless, recording or checking its transcript
+    synacor dump-image          writes the memory image after running the binary
+
+Every subcommand runs the binary given with `--binary` (files/challenge.bin by default) and prints its usage and flags
+with `--help`. The flags of each subcommand are declared along with their help, so they're parsed into values looked
+up by name while the other arguments are left as positional ones. The older binaries run their subcommand, and the ones
+without a subcommand take the binary with `--binary` as well.
+*/
+
+const DEFAULT_BINARY: &str = "files/challenge.bin";
+
+const BINARY: Flag = Flag { name: "--binary", value: Some("<file>"), help: "binary to run (files/challenge.bin by default)" };
+const INPUT: Flag = Flag { name: "--input", value: Some("<script>"), help: "input script with the commands to run first" };
+
+const COMMANDS: [&Command; 9] = [
+    &run::RUN,
+    &debug::DEBUG,
+    &disasm::DISASM,
+    &graph::GRAPH,
+    &solve::TELEPORTER,
+    &solve::VAULT,
+    &solve::COINS,
+    &replay::REPLAY,
+    &dump_image::DUMP_IMAGE
+];
+
+struct Flag {
+    name: &'static str,
+    value: Option<&'static str>,
+    help: &'static str
+}
+
+struct Command {
+    name: &'static str,
+    positional: &'static str,
+    about: &'static str,
+    flags: &'static [Flag],
+    run: fn(&Arguments) -> Result<(), String>
+}
+
+struct Arguments {
+    values: HashMap<&'static str, String>,
+    positional: Vec<String>
+}
+
+// Runs the subcommand given by the words along with the arguments of the process, exiting on errors
+pub fn main(command: &[&str]) {
+    let args: Vec<String> = command.iter().map(|word| word.to_string()).chain(env::args().skip(1)).collect();
+
+    if let Err(error) = execute(&args) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+pub fn execute(args: &[String]) -> Result<(), String> {
+    let command = COMMANDS.iter().find(|command| {
+        let words: Vec<&str> = command.name.split(' ').collect();
+
+        args.len() >= words.len() && args[.. words.len()] == words
+    });
+
+    match (command, args.first().map(String::as_str)) {
+        (Some(command), _) => {
+            let args = &args[command.name.split(' ').count() ..];
+
+            if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+                print!("{}", command.help());
+                return Ok(());
+            }
+
+            (command.run)(&Arguments::parse(command, args)?)
+        }
+        (None, None | Some("help" | "--help" | "-h")) => {
+            print!("{}", help());
+            Ok(())
+        }
+        (None, Some(_)) => Err(format!("unknown command {}\n\n{}", args.join(" "), help()))
+    }
+}
+
+// VM loaded with the binary
+pub fn load(file_path: &str) -> Result<VM, String> {
+    let image = read_image(file_path)?;
+    let mut vm = VM::new();
+
+    vm.load_image(&image);
+    Ok(vm)
+}
+
+// Binary given with --binary (files/challenge.bin by default) and the rest of the arguments, for the older binaries
+pub fn binary_argument(mut args: Vec<String>) -> Result<(String, Vec<String>), String> {
+    let Some(position) = args.iter().position(|arg| arg == BINARY.name) else { return Ok((DEFAULT_BINARY.to_string(), args)) };
+
+    if position + 1 == args.len() {
+        return Err(format!("{} should be followed by a binary", BINARY.name));
+    }
+
+    let binary = args.remove(position + 1);
+
+    args.remove(position);
+    Ok((binary, args))
+}
+
+fn read(file_path: &str) -> Result<String, String> {
+    fs::read_to_string(file_path).map_err(|_| format!("Unable to read {}", file_path))
+}
+
+fn help() -> String {
+    let width = COMMANDS.iter().map(|command| command.name.len()).max().unwrap_or(0);
+    let commands: String = COMMANDS
+        .iter()
+        .map(|command| format!("    {:<width$}    {}\n", command.name, command.about, width = width))
+        .collect();
+
+    format!("usage: synacor <command> [options]\n\ncommands:\n{}\nsee synacor <command> --help for the options of each one\n", commands)
+}
+
+impl Command {
+    fn usage(&self) -> String {
+        let positional = if self.positional.is_empty() { String::new() } else { format!(" {}", self.positional) };
+
+        format!("usage: synacor {} [options]{}", self.name, positional)
+    }
+
+    fn help(&self) -> String {
+        let flag = |flag: &Flag| flag.value.map_or(flag.name.to_string(), |value| format!("{} {}", flag.name, value));
+        let width = self.flags.iter().map(|f| flag(f).len()).max().unwrap_or(0).max("--help".len());
+        let flags: String = self.flags
+            .iter()
+            .map(|f| (flag(f), f.help))
+            .chain([("--help".to_string(), "prints this help")])
+            .map(|(flag, help)| format!("    {:<width$}    {}\n", flag, help, width = width))
+            .collect();
+
+        format!("{}\n\n{}\n\noptions:\n{}", self.usage(), self.about, flags)
+    }
+}
+
+impl Arguments {
+    fn parse(command: &Command, args: &[String]) -> Result<Self, String> {
+        let mut arguments = Self { values: HashMap::new(), positional: vec![] };
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match command.flags.iter().find(|flag| flag.name == arg) {
+                Some(flag) => {
+                    let value = match flag.value {
+                        Some(value) => args.next().ok_or_else(|| format!("{} should be followed by {}\n{}", arg, value, command.usage()))?,
+                        None        => ""
+                    };
+
+                    arguments.values.insert(flag.name, value.to_string());
+                }
+                None if arg.starts_with("--") || command.positional.is_empty() => {
+                    return Err(format!("unknown argument {}\n{}", arg, command.usage()));
+                }
+                None => arguments.positional.push(arg.clone())
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    fn parse_value<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.value(name)
+            .map(|value| value.parse().map_err(|_| format!("{} {} isn't valid", name, value)))
+            .transpose()
+    }
+
+    // Format given with --format or the shorthand flag of one of them (--<format>), the first one by default
+    fn format(&self, formats: &[&'static str]) -> Result<&str, String> {
+        let format = formats
+            .iter()
+            .copied()
+            .find(|format| self.has(&format!("--{}", format)))
+            .or(self.value("--format"))
+            .unwrap_or(formats[0]);
+
+        match formats.contains(&format) {
+            true  => Ok(format),
+            false => Err(format!("unknown format {}, expected one of {}", format, formats.join(", ")))
+        }
+    }
+
+    fn load(&self) -> Result<VM, String> {
+        load(self.value(BINARY.name).unwrap_or(DEFAULT_BINARY))
+    }
+
+    // Lines of the input script, if any
+    fn input(&self) -> Result<String, String> {
+        self.value(INPUT.name).map_or(Ok(String::new()), read)
+    }
+}
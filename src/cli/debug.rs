@@ -0,0 +1,91 @@
+use std::{env, io::{stdin, stdout, IsTerminal, Write}};
+use crate::{completion::complete, debugger::Debugger, discovery::discover, editor::Editor, tui::Tui};
+use super::{Arguments, Command, Flag, BINARY, INPUT};
+
+/*
+The debugger reads its lines from the input script and then stdin. When stdin is a terminal (and the TUI isn't used) the
+lines are typed through the line editor (see editor.rs), with their history kept in a file and completed from the
+debugger commands, the symbols and the game world (see completion.rs). The symbols of the game are discovered on a copy
+of the VM before starting.
+*/
+
+pub(super) const DEBUG: Command = Command {
+    name: "debug",
+    positional: "",
+    about: "runs the binary through the debugger, reading from the input script and then stdin",
+    flags: &[
+        BINARY,
+        INPUT,
+        Flag { name: "--tui", value: None, help: "full screen debugger with the disassembly, registers, stack, memory and game panes" },
+        Flag { name: "--history", value: Some("<file>"), help: "history of the typed lines (~/.synacor_history by default)" }
+    ],
+    run: debug
+};
+
+fn print(output: &str) {
+    print!("{}", output);
+    stdout().flush().ok();
+}
+
+fn debug(arguments: &Arguments) -> Result<(), String> {
+    let vm = arguments.load()?;
+    let mut copy = vm.clone();
+
+    copy.dbg_set_output_enabled(false);
+
+    let layout = discover(&mut copy);
+    let symbols = layout.symbols(&layout.read_world(copy.dbg_get_memory()));
+    let mut debugger = Debugger::new(vm).symbols(symbols).layout(layout);
+    let script = arguments.input()?;
+
+    if arguments.has("--tui") {
+        let mut tui = Tui::new(debugger);
+
+        for line in script.lines() {
+            if !tui.execute(line) {
+                return Ok(());
+            }
+        }
+
+        return tui.run();
+    }
+
+    let interactive = stdin().is_terminal();
+    let mut lines: Vec<String> = script.lines().map(String::from).collect();
+
+    // The game runs until it asks for the first command, so its strings are decrypted before completing them
+    if interactive && lines.is_empty() {
+        lines.push("$ continue".to_string());
+    }
+
+    let lines: Box<dyn Iterator<Item = String>> = match interactive {
+        true  => Box::new(lines.into_iter()),
+        false => Box::new(lines.into_iter().chain(stdin().lines().map_while(Result::ok)))
+    };
+
+    for line in lines {
+        match debugger.execute(&format!("{}\n", line)) {
+            Some(output) => print(&output),
+            None         => return Ok(())
+        }
+    }
+
+    if !interactive {
+        return Ok(());
+    }
+
+    let history_file = match arguments.value("--history") {
+        Some(file_path) => file_path.to_string(),
+        None            => format!("{}/.synacor_history", env::var("HOME").unwrap_or(".".to_string()))
+    };
+    let mut editor = Editor::default().history_file(&history_file);
+
+    while let Some(line) = editor.read_line("> ", |line| complete(line, debugger.vm.dbg_get_memory(), &debugger.layout, &debugger.symbols))? {
+        match debugger.execute(&format!("{}\n", line)) {
+            Some(output) => print(&output),
+            None         => break
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use crate::asm::{Listing, Syntax};
+use super::{Arguments, Command, Flag, BINARY};
+
+const LAST_CODE_POSITION: u16 = 6089;
+
+pub(super) const DISASM: Command = Command {
+    name: "disasm",
+    positional: "",
+    about: "disassembles the binary in the arch-spec mnemonics or a pseudo-code syntax",
+    flags: &[
+        BINARY,
+        Flag { name: "--format", value: Some("<pseudo|spec>"), help: "syntax of the instructions (pseudo by default)" },
+        Flag { name: "--pseudo", value: None, help: "same as --format pseudo" },
+        Flag { name: "--spec", value: None, help: "same as --format spec" },
+        Flag { name: "--raw", value: None, help: "prints the raw words of each instruction" },
+        Flag { name: "--from", value: Some("<address>"), help: "first address (0 by default)" },
+        Flag { name: "--to", value: Some("<address>"), help: "last address (6089 by default, the end of the code)" }
+    ],
+    run: disasm
+};
+
+fn disasm(arguments: &Arguments) -> Result<(), String> {
+    let syntax = match arguments.format(&["pseudo", "spec"])? {
+        "spec" => Syntax::Spec,
+        _      => Syntax::Pseudo
+    };
+    let from = arguments.parse_value("--from")?.unwrap_or(0);
+    let to = arguments.parse_value("--to")?.unwrap_or(LAST_CODE_POSITION);
+    let vm = arguments.load()?;
+
+    Listing::new(vm.dbg_get_memory())
+        .syntax(syntax)
+        .raw_words(arguments.has("--raw"))
+        .lines_from(from)
+        .take_while(|line| line.address <= to)
+        .for_each(|line| println!("{}", line));
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::{fs, io::{stdout, Write}};
+use crate::{debugger::Debugger, MAX_SIZE};
+use super::{Arguments, Command, Flag, BINARY, INPUT};
+
+/*
+The memory is dumped once the binary waits for input and went through the input script, which runs in the debugger so
+its commands can change the memory first. Since the game decrypts its strings while starting, the dumped image differs
+from the binary even without a script.
+*/
+
+const WORDS_PER_LINE: usize = 8;
+
+pub(super) const DUMP_IMAGE: Command = Command {
+    name: "dump-image",
+    positional: "",
+    about: "writes the memory image after running the input script",
+    flags: &[
+        BINARY,
+        INPUT,
+        Flag { name: "--format", value: Some("<words|binary>"), help: "addresses and words in text (words, default) or a binary" },
+        Flag { name: "--from", value: Some("<address>"), help: "first address (0 by default)" },
+        Flag { name: "--to", value: Some("<address>"), help: "last address (32767 by default)" },
+        Flag { name: "--output", value: Some("<file>"), help: "file to write instead of stdout" }
+    ],
+    run: dump_image
+};
+
+fn dump_image(arguments: &Arguments) -> Result<(), String> {
+    let format = arguments.format(&["words", "binary"])?;
+    let from: usize = arguments.parse_value("--from")?.unwrap_or(0);
+    let to: usize = arguments.parse_value("--to")?.unwrap_or(MAX_SIZE - 1);
+
+    if from > to || to >= MAX_SIZE {
+        return Err(format!("the addresses should be between 0 and {} in order", MAX_SIZE - 1));
+    }
+
+    let mut debugger = Debugger::new(arguments.load()?);
+
+    debugger.vm.run();
+
+    for line in arguments.input()?.lines() {
+        if debugger.execute(&format!("{}\n", line)).is_none() {
+            break;
+        }
+    }
+
+    let image = &debugger.vm.dbg_get_memory()[from ..= to];
+    let bytes: Vec<u8> = match format {
+        "binary" => image.iter().flat_map(|word| word.to_le_bytes()).collect(),
+        _        => image
+            .chunks(WORDS_PER_LINE)
+            .enumerate()
+            .map(|(idx, words)| {
+                let words: Vec<String> = words.iter().map(|word| format!("{:>5}", word)).collect();
+
+                format!("{:>5}: {}\n", from + idx * WORDS_PER_LINE, words.join(" "))
+            })
+            .collect::<String>()
+            .into_bytes()
+    };
+
+    match arguments.value("--output") {
+        Some(file_path) => fs::write(file_path, bytes).map_err(|_| format!("Unable to write {}", file_path)),
+        None            => stdout().write_all(&bytes).map_err(|_| "Unable to write to stdout".to_string())
+    }
+}
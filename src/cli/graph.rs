@@ -0,0 +1,72 @@
+use crate::{
+    dataflow::Program,
+    discovery::discover,
+    export::{to_dot, to_graphml, to_json, to_mermaid},
+    handlers::Handler,
+    world::World,
+    VM
+};
+use super::{Arguments, Command, Flag, BINARY};
+
+pub(super) const GRAPH: Command = Command {
+    name: "graph",
+    positional: "",
+    about: "exports the locations of the game world, their connections and items",
+    flags: &[
+        BINARY,
+        Flag { name: "--format", value: Some("<dot|handlers|json|mermaid|graphml>"), help: "output format (dot by default)" },
+        Flag { name: "--handlers", value: None, help: "same as --format handlers, the analysis of the enter and use fns" },
+        Flag { name: "--json", value: None, help: "same as --format json" },
+        Flag { name: "--mermaid", value: None, help: "same as --format mermaid" },
+        Flag { name: "--graphml", value: None, help: "same as --format graphml" },
+        Flag { name: "--collapse-maze", value: None, help: "collapses each maze into a single location" }
+    ],
+    run: graph
+};
+
+fn print_handlers(vm: &VM, world: &World, current_location: u16) {
+    let handlers: Vec<(String, u16)> = world.locations
+        .iter()
+        .map(|location| (format!("enter fn of [{}] {}", location.id, location.name), location.enter_fn))
+        .chain(world.items.iter().map(|item| (format!("use fn of [{}] {}", item.id, item.name), item.use_fn)))
+        .filter(|&(_, handler)| handler != 0)
+        .collect();
+    let program = Program::lift(vm.dbg_get_memory(), &handlers.iter().map(|&(_, handler)| handler).collect::<Vec<u16>>());
+
+    for (name, address) in handlers {
+        let mut handler = Handler::analyze(&program, address, current_location);
+
+        handler.emulate_prints(vm, &program);
+
+        println!("{}", name);
+        println!("{}", handler);
+        println!("{}", program.functions[&address]);
+    }
+}
+
+fn graph(arguments: &Arguments) -> Result<(), String> {
+    let format = arguments.format(&["dot", "handlers", "json", "mermaid", "graphml"])?;
+    let mut vm = arguments.load()?;
+
+    vm.dbg_set_output_enabled(false);
+
+    let layout = discover(&mut vm);
+
+    for fallback in &layout.fallbacks {
+        eprintln!("{} not found, using the one of the original binary", fallback);
+    }
+
+    let world = layout.read_world(vm.dbg_get_memory());
+    let world = if arguments.has("--collapse-maze") { world.collapse_mazes() } else { world };
+    let highlighted = [layout.start_location, layout.end_location];
+
+    match format {
+        "handlers" => print_handlers(&vm, &world, layout.current_location),
+        "json"     => print!("{}", to_json(&world)),
+        "mermaid"  => print!("{}", to_mermaid(&world, &highlighted)),
+        "graphml"  => print!("{}", to_graphml(&world, &highlighted)),
+        _          => print!("{}", to_dot(&world, &highlighted))
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use std::fs;
+use crate::replay::{compare, replay};
+use super::{read, Arguments, Command, Flag, BINARY, INPUT};
+
+pub(super) const REPLAY: Command = Command {
+    name: "replay",
+    positional: "[<script>]",
+    about: "replays an input script headless, recording or checking its transcript",
+    flags: &[
+        BINARY,
+        INPUT,
+        Flag { name: "--record", value: Some("<transcript>"), help: "writes the transcript to the file instead of printing it" },
+        Flag { name: "--check", value: Some("<transcript>"), help: "reports the first line differing from the transcript" }
+    ],
+    run: replay_script
+};
+
+fn replay_script(arguments: &Arguments) -> Result<(), String> {
+    let script = match (&arguments.positional[..], arguments.value(INPUT.name)) {
+        ([script], None)   => read(script)?,
+        ([], Some(script)) => read(script)?,
+        _                  => return Err(format!("replay should be given one input script\n{}", REPLAY.usage()))
+    };
+    let transcript = replay(arguments.load()?, &script);
+
+    match (arguments.value("--record"), arguments.value("--check")) {
+        (None, None) => print!("{}", transcript),
+        (Some(golden), None) => fs::write(golden, transcript).map_err(|_| format!("Unable to write {}", golden))?,
+        (None, Some(golden)) => {
+            if let Some(difference) = compare(&read(golden)?, &transcript) {
+                return Err(difference.to_string());
+            }
+        }
+        _ => return Err(format!("--record and --check can't be given together\n{}", REPLAY.usage()))
+    }
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::io::{stdin, stdout, Write};
+use super::{Arguments, Command, BINARY, INPUT};
+
+pub(super) const RUN: Command = Command {
+    name: "run",
+    positional: "",
+    about: "runs the binary reading the game commands from the input script and then stdin",
+    flags: &[BINARY, INPUT],
+    run
+};
+
+fn run(arguments: &Arguments) -> Result<(), String> {
+    let mut vm = arguments.load()?;
+
+    // Debugger commands are left to `synacor debug`
+    for line in arguments.input()?.lines().filter(|line| !line.starts_with('$')) {
+        vm.input_command(&format!("{}\n", line));
+    }
+
+    loop {
+        vm.run();
+        stdout().flush().ok();
+
+        let mut input = String::new();
+
+        if vm.is_halted() || stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            return Ok(());
+        }
+
+        vm.input_command(&input);
+    }
+}
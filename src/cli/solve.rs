@@ -0,0 +1,216 @@
+use std::io::{stderr, Write};
+use crate::{
+    coins::{find_monument, read_coins, solve, verify as verify_coins},
+    discovery::{discover, Layout},
+    teleporter::{Check, EmulatedCheck, FormulaCheck, Search, JMP},
+    vault::{verify as verify_walk, Grid, Position, Solver, Step, OPERATORS},
+    VM
+};
+use super::{read, Arguments, Command, Flag, BINARY};
+
+/*
+Solvers of the puzzles of the game, reading their data from the binary:
+
+The teleporter check is the r7 for which the energy level verification function gives the expected value, using either
+the emulation of the function of the binary or, as a faster path, the formula derived for the original binary
+(`--formula`) for every candidate r7, whose solutions are then confirmed through the emulation since a variant of the
+function could be called with the same arguments. The expected value and the place where the check can be skipped are
+discovered on the binary, since they depend on its layout, although the entry point of the emulated function, the target
+value and the range of candidates can be given. The candidates are searched across threads until a solution is found, or
+all of them with `--exhaustive`, printing the progress to stderr.
+
+The rooms controlling the vault door are organized in a grid, each of which contains either a number or an operator, such
+as the following 4 x 4 one in the original binary:
+
+     *  8  -  1
+     4  * 11  *
+     +  4  - 18
+    22  -  9  *
+
+Starting in the room with the weight of the orb (22), the goal is to reach the vault door (1) in the least number of steps
+such that the value of the traversed path considering left-associative, same precedence operators, equals the weight
+carved in the door (30). The grid is read from the rooms of the game world, unless another one is given along with its
+start and end rooms and the target weight. Every shortest walk is listed with the weight of the orb after each step, and
+the first one is then followed in the game (unless the grid was given) to check that the door opens before printing its
+directions.
+
+The coins are placed in the monument in the order solving its equation (see coins.rs), which can be verified in the game.
+*/
+
+pub(super) const TELEPORTER: Command = Command {
+    name: "solve teleporter",
+    positional: "",
+    about: "solves the r7 passing the teleporter check, printing the commands that set it and skip the check",
+    flags: &[
+        BINARY,
+        Flag { name: "--formula", value: None, help: "uses the formula derived for the original binary, confirming its solutions" },
+        Flag { name: "--entry", value: Some("<address>"), help: "entry point of the emulated function (discovered by default)" },
+        Flag { name: "--target", value: Some("<value>"), help: "value the check should give (discovered by default)" },
+        Flag { name: "--from", value: Some("<r7>"), help: "first candidate (0 by default)" },
+        Flag { name: "--to", value: Some("<r7>"), help: "last candidate (32767 by default)" },
+        Flag { name: "--threads", value: Some("<count>"), help: "threads searching the candidates (one per core by default)" },
+        Flag { name: "--exhaustive", value: None, help: "reports every solution instead of the first one" }
+    ],
+    run: teleporter
+};
+
+pub(super) const VAULT: Command = Command {
+    name: "solve vault",
+    positional: "",
+    about: "solves the walks through the rooms opening the vault door, verifying them in the game",
+    flags: &[
+        BINARY,
+        Flag { name: "--grid", value: Some("<file>"), help: "rows of numbers and operators to solve instead of the game grid" },
+        Flag { name: "--start", value: Some("<row>,<column>"), help: "start room of the given grid" },
+        Flag { name: "--end", value: Some("<row>,<column>"), help: "end room of the given grid" },
+        Flag { name: "--target", value: Some("<weight>"), help: "weight the orb should have in the end room of the given grid" }
+    ],
+    run: vault
+};
+
+pub(super) const COINS: Command = Command {
+    name: "solve coins",
+    positional: "",
+    about: "solves the order of the coins in the equation of the monument",
+    flags: &[
+        BINARY,
+        Flag { name: "--verify", value: None, help: "places the coins in the game to check that the door opens" }
+    ],
+    run: coins
+};
+
+fn search(check: &impl Check, search: &Search, from: u16, to: u16) -> Result<Vec<u16>, String> {
+    let mut progress = |checked: usize, total: usize| {
+        eprint!("\rchecked {} of {} candidates", checked, total);
+        stderr().flush().ok();
+    };
+    let solutions = search.run(check, from ..= to, &mut progress)?;
+
+    eprintln!();
+    Ok(solutions)
+}
+
+fn teleporter(arguments: &Arguments) -> Result<(), String> {
+    let mut vm = arguments.load()?;
+
+    vm.dbg_set_output_enabled(false);
+
+    let teleporter = discover(&mut vm).teleporter;
+    let entry = arguments.parse_value("--entry")?.unwrap_or(teleporter.function);
+    let target = arguments.parse_value("--target")?.unwrap_or(teleporter.expected);
+    let from = arguments.parse_value("--from")?.unwrap_or(0);
+    let to = arguments.parse_value("--to")?.unwrap_or(32767);
+    let mut searcher = Search::new(target).exhaustive(arguments.has("--exhaustive"));
+
+    if let Some(threads) = arguments.parse_value("--threads")? {
+        searcher = searcher.threads(threads);
+    }
+
+    let emulated = EmulatedCheck::new(vm.dbg_get_memory(), entry, teleporter.arguments)?;
+    let solutions = match arguments.has("--formula") {
+        true  => search(&FormulaCheck::new(teleporter.arguments)?, &searcher, from, to)?,
+        false => search(&emulated, &searcher, from, to)?
+    };
+
+    // The formula only holds for the check of the original binary
+    if arguments.has("--formula") {
+        for &r7 in &solutions {
+            let value = emulated.evaluate(r7)?;
+
+            if value != target {
+                return Err(format!("the formula gives r7 = {} but the check of the binary gives {} instead of {}", r7, value, target));
+            }
+        }
+    }
+
+    if solutions.is_empty() {
+        return Err(format!("no r7 between {} and {} gives {}", from, to, target));
+    }
+
+    for r7 in &solutions {
+        println!("r7 = {}", r7);
+    }
+
+    println!();
+    println!("$ set_register 7 {}", solutions[0]);
+    println!("$ set_memory {} {}", teleporter.bypass, JMP);
+    println!("$ set_memory {} {}", teleporter.bypass + 1, teleporter.success);
+    Ok(())
+}
+
+fn parse_position(value: &str) -> Result<Position, String> {
+    value
+        .split_once(',')
+        .and_then(|(row, column)| Some((row.trim().parse().ok()?, column.trim().parse().ok()?)))
+        .ok_or_else(|| format!("{} should be <row>,<column>", value))
+}
+
+fn read_grid(arguments: &Arguments) -> Result<(Grid, Option<(VM, Layout)>), String> {
+    let (file_path, start, end) = (arguments.value("--grid"), arguments.value("--start"), arguments.value("--end"));
+
+    match (file_path, start, end, arguments.parse_value("--target")?) {
+        (Some(file_path), Some(start), Some(end), Some(target)) => {
+            Ok((Grid::parse(&read(file_path)?, &OPERATORS, parse_position(start)?, parse_position(end)?, target)?, None))
+        }
+        (None, None, None, None) => {
+            let mut vm = arguments.load()?;
+
+            vm.dbg_set_output_enabled(false);
+
+            let layout = discover(&mut vm);
+            let world = layout.read_world(vm.dbg_get_memory());
+
+            Ok((Grid::read(&world, &OPERATORS)?, Some((vm, layout))))
+        }
+        _ => Err(format!("--grid, --start, --end and --target should be given together\n{}", VAULT.usage()))
+    }
+}
+
+fn print_walk(grid: &Grid, walk: &[Step]) {
+    for (idx, step) in walk.iter().enumerate() {
+        println!("{:>4}. {:<5} to {:?} {:>3}  weight {}", idx + 1, step.direction, step.position, grid.cell(step.position).to_string(), step.weight);
+    }
+}
+
+fn vault(arguments: &Arguments) -> Result<(), String> {
+    let (grid, game) = read_grid(arguments)?;
+    let walks = Solver::new(&grid).solutions()?;
+
+    for (idx, walk) in walks.iter().enumerate() {
+        println!("Walk {} of {} ({} steps):", idx + 1, walks.len(), walk.len());
+        print_walk(&grid, walk);
+        println!();
+    }
+
+    if let Some((vm, layout)) = game {
+        verify_walk(&vm, &layout, &grid, &walks[0]).map_err(|error| format!("walk 1 failed in the game at {}", error))?;
+        println!("Walk 1 opens the vault door in the game");
+        println!();
+    }
+
+    println!("{}", walks[0].iter().map(|step| step.direction).collect::<Vec<&str>>().join("\n"));
+    Ok(())
+}
+
+fn coins(arguments: &Arguments) -> Result<(), String> {
+    let mut vm = arguments.load()?;
+
+    vm.dbg_set_output_enabled(false);
+
+    let layout = discover(&mut vm);
+    let world = layout.read_world(vm.dbg_get_memory());
+    let coins = read_coins(vm.dbg_get_memory(), &world);
+    let monument = find_monument(&world)?;
+    let order = solve(&coins, &monument.equation)?;
+
+    for coin in &order {
+        println!("use {}", coin.name);
+    }
+
+    if arguments.has("--verify") {
+        verify_coins(&vm, &layout, &monument, &order).map_err(|output| format!("not verified, the game says:\n{}", output))?;
+        eprintln!("verified: the door opens");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+
+/*
+Codes are announced by the game (or the arch-spec, for the first one) with a fixed text right before them, so each code
+is the word of letters and digits following its announcement, skipping spaces and quotes:
+
+    hints:       Here's a code for the challenge website: <code>
+    welcome:     this one into the challenge website: <code>
+    self-test:   The self-test completion code is: <code>
+    tablet:      You find yourself writing "<code>" on the tablet.
+    wall:        Chiseled on the wall of one of the passageways, you see: <code>
+    teleporter:  you think you see a pattern in the stars... <code>
+    beach:       Someone seems to have drawn a message in the sand here: <code>
+    mirror:      Through the mirror, you see "<code>" scrawled in charcoal on your forehead.
+
+The code seen through the mirror has to be mirrored back: reversed and with the letters which are mirror images of each
+other swapped (b and d, p and q).
+*/
+
+pub struct Announcement {
+    pub source: &'static str,
+    pub text: &'static str,
+    pub mirrored: bool
+}
+
+pub const ANNOUNCEMENTS: [Announcement; 8] = [
+    Announcement { source: "hints", text: "Here's a code for the challenge website:", mirrored: false },
+    Announcement { source: "welcome", text: "this one into the challenge website:", mirrored: false },
+    Announcement { source: "self-test", text: "The self-test completion code is:", mirrored: false },
+    Announcement { source: "tablet", text: "You find yourself writing", mirrored: false },
+    Announcement { source: "wall", text: "Chiseled on the wall of one of the passageways, you see:", mirrored: false },
+    Announcement { source: "teleporter", text: "you think you see a pattern in the stars...", mirrored: false },
+    Announcement { source: "beach", text: "Someone seems to have drawn a message in the sand here:", mirrored: false },
+    Announcement { source: "mirror", text: "Through the mirror, you see", mirrored: true }
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Code {
+    pub number: usize,
+    pub source: &'static str,
+    pub seen: String,
+    pub value: String
+}
+
+// Codes found so far, numbered by their announcement
+#[derive(Default)]
+pub struct Codes {
+    pub found: Vec<Code>
+}
+
+pub fn mirror(code: &str) -> String {
+    code.chars()
+        .rev()
+        .map(|c| match c {
+            'b' => 'd',
+            'd' => 'b',
+            'p' => 'q',
+            'q' => 'p',
+            c   => c
+        })
+        .collect()
+}
+
+pub fn extract_codes(text: &str) -> Vec<Code> {
+    let mut codes = vec![];
+
+    for (idx, announcement) in ANNOUNCEMENTS.iter().enumerate() {
+        for (position, _) in text.match_indices(announcement.text) {
+            let seen: String = text[position + announcement.text.len() ..]
+                .trim_start_matches(|c: char| c.is_whitespace() || c == '"')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect();
+
+            if !seen.is_empty() {
+                let value = if announcement.mirrored { mirror(&seen) } else { seen.clone() };
+
+                codes.push(Code { number: idx + 1, source: announcement.source, seen, value });
+            }
+        }
+    }
+
+    codes
+}
+
+impl Codes {
+    // Records the codes announced in the text which weren't found before, returning them
+    pub fn watch(&mut self, text: &str) -> Vec<Code> {
+        let mut new_codes = vec![];
+
+        for code in extract_codes(text) {
+            if !self.found.contains(&code) {
+                self.found.push(code.clone());
+                new_codes.push(code);
+            }
+        }
+
+        self.found.sort_by_key(|code| code.number);
+        new_codes
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut table = String::new();
+
+        writeln!(table, "{:>2}  {:<10}  code", "#", "source").unwrap();
+
+        for (idx, announcement) in ANNOUNCEMENTS.iter().enumerate() {
+            let codes: Vec<&Code> = self.found.iter().filter(|code| code.number == idx + 1).collect();
+
+            if codes.is_empty() {
+                writeln!(table, "{:>2}  {:<10}  -", idx + 1, announcement.source).unwrap();
+            }
+
+            for code in codes {
+                let seen = if code.seen != code.value { format!(" (seen as {})", code.seen) } else { String::new() };
+
+                writeln!(table, "{:>2}  {:<10}  {}{}", code.number, code.source, code.value, seen).unwrap();
+            }
+        }
+
+        table
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    dataflow::{AbstractValue, Program, Values},
+    discovery::Layout,
+    world::{move_item, move_player, World, INVENTORY},
+    VM
+};
+
+/*
+The monument in the ruins has an equation with a slot for each coin, such as:
+
+    _ + _ * _^2 + _^3 - _ = 399
+
+Coins are the items whose use fn calls the slot function with the item and its value (call f(r0=item, r1=value)), so
+the values are read from those calls. Every order of the coins is tried on the equation, which is evaluated with the
+usual precedence, and the solution can be verified by placing the coins in the VM from the monument.
+*/
+
+const DOOR_OPENED: &str = "you hear a click";
+
+// Sign and powers of the slots of each term
+type Terms = Vec<(i64, Vec<u32>)>;
+
+pub struct Coin {
+    pub id: usize,
+    pub name: String,
+    pub value: i64
+}
+
+pub struct Monument {
+    pub location: usize,
+    pub equation: String
+}
+
+pub fn read_coins(memory: &[u16], world: &World) -> Vec<Coin> {
+    let use_fns: Vec<u16> = world.items.iter().map(|item| item.use_fn).filter(|&use_fn| use_fn != 0).collect();
+    let program = Program::lift(memory, &use_fns);
+
+    world.items
+        .iter()
+        .filter(|item| item.use_fn != 0)
+        .filter_map(|item| {
+            let function = &program.functions[&item.use_fn];
+            let values = Values::compute(function, &program.summaries);
+
+            function.call_sites().iter().find_map(|(site, _)| match values.before[site].registers[.. 2] {
+                [AbstractValue::Const(r0), AbstractValue::Const(r1)] if r0 as usize == item.id => {
+                    Some(Coin { id: item.id, name: item.name.clone(), value: r1 as i64 })
+                }
+                _ => None
+            })
+        })
+        .collect()
+}
+
+// Location whose description has the equation
+pub fn find_monument(world: &World) -> Result<Monument, String> {
+    world.locations
+        .iter()
+        .find_map(|location| location.description
+            .lines()
+            .find(|line| line.contains('_') && line.contains('='))
+            .map(|equation| Monument { location: location.id, equation: equation.to_string() }))
+        .ok_or_else(|| "the monument with the equation should be in the world".to_string())
+}
+
+// Terms of the equation along with the expected result
+fn parse_equation(equation: &str) -> Option<(Terms, i64)> {
+    let (expression, result) = equation.split_once('=')?;
+    let mut terms = vec![(1, vec![])];
+
+    for token in expression.split_whitespace() {
+        match token {
+            "+" => terms.push((1, vec![])),
+            "-" => terms.push((-1, vec![])),
+            "*" => (),
+            _   => {
+                let power = match token.strip_prefix('_')?.strip_prefix('^') {
+                    Some(power) => power.parse().ok()?,
+                    None        => 1
+                };
+
+                terms.last_mut()?.1.push(power);
+            }
+        }
+    }
+
+    Some((terms, result.trim().parse().ok()?))
+}
+
+fn evaluate(terms: &[(i64, Vec<u32>)], values: &[i64]) -> i64 {
+    let mut slots = values.iter();
+
+    terms
+        .iter()
+        .map(|(sign, powers)| sign * powers.iter().map(|&power| slots.next().unwrap().pow(power)).product::<i64>())
+        .sum()
+}
+
+fn permutations(size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+
+    permutations(size - 1)
+        .into_iter()
+        .flat_map(|permutation| (0 ..= permutation.len()).map(move |idx| {
+            let mut extended = permutation.clone();
+
+            extended.insert(idx, size - 1);
+            extended
+        }))
+        .collect()
+}
+
+// Order in which the coins solve the equation
+pub fn solve<'a>(coins: &'a [Coin], equation: &str) -> Result<Vec<&'a Coin>, String> {
+    let (terms, result) = parse_equation(equation).ok_or_else(|| format!("{} should be an equation", equation))?;
+    let slots: usize = terms.iter().map(|(_, powers)| powers.len()).sum();
+
+    if slots != coins.len() {
+        return Err(format!("there should be a coin for each slot of {}", equation));
+    }
+
+    permutations(coins.len())
+        .into_iter()
+        .find(|order| evaluate(&terms, &order.iter().map(|&idx| coins[idx].value).collect::<Vec<i64>>()) == result)
+        .map(|order| order.iter().map(|&idx| &coins[idx]).collect())
+        .ok_or_else(|| "no order of the coins solves the equation".to_string())
+}
+
+// Places the coins in the given order from the monument, returning what the game says if the door doesn't open
+pub fn verify(vm: &VM, layout: &Layout, monument: &Monument, order: &[&Coin]) -> Result<(), String> {
+    let mut vm = vm.clone();
+
+    for coin in order {
+        move_item(&mut vm, coin.id, INVENTORY);
+    }
+
+    move_player(&mut vm, layout.current_location, monument.location);
+    vm.dbg_set_output_enabled(false);
+    vm.dbg_set_output_capture(true);
+
+    for coin in order {
+        vm.input_command(&format!("use {}\n", coin.name));
+        vm.run();
+    }
+
+    let output = vm.dbg_take_captured_output();
+
+    match output.contains(DOOR_OPENED) {
+        true  => Ok(()),
+        false => Err(output)
+    }
+}
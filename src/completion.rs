@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+use crate::{
+    debugger::{ADDRESS_COMMANDS, COMMANDS},
+    discovery::Layout,
+    world::{get_current_location, get_items, is_readable, INVENTORY}
+};
+
+/*
+Lines typed in the debugger are completed from what they start with:
+
+    $ <command>                 debugger commands
+    $ <command> <address>       symbols, for the commands taking an address
+    <verb or exit>              game verbs and the exits of the current location
+    go <exit>                   exits of the current location
+    take <item>                 items in the current location
+    drop / use <item>           items in the inventory
+    look <item>                 items in the current location or the inventory
+
+The location and items are read from the memory as it is when completing, so they follow the game once it has started.
+Item names may have several words, so the whole argument is completed at once.
+*/
+
+const VERBS: [&str; 7] = ["go", "look", "take", "drop", "use", "inv", "help"];
+
+// Lines starting with the given one, completing its last word (or the argument of the verb)
+pub fn complete(line: &str, memory: &[u16], layout: &Layout, symbols: &BTreeMap<String, u16>) -> Vec<String> {
+    let location = memory[layout.current_location as usize] as usize;
+
+    // Nothing of the game is completed until its strings are decrypted
+    let (exits, all_items) = match is_readable(memory, layout.current_location) {
+        true  => (get_current_location(memory, layout.current_location).connections, get_items(memory, layout.items_table)),
+        false => (vec![], vec![])
+    };
+    let exits = || exits.iter().map(|exit| exit.label.clone()).collect::<Vec<String>>();
+    let items = |locations: &[usize]| {
+        all_items
+            .iter()
+            .filter(|item| locations.contains(&item.location))
+            .map(|item| item.name.clone())
+            .collect::<Vec<String>>()
+    };
+    let (prefix, partial, options) = match line.strip_prefix('$') {
+        Some(rest) => match rest.trim_start().split_once(' ') {
+            None => ("$ ".to_string(), rest.trim_start(), COMMANDS.iter().map(|command| command.to_string()).collect()),
+            Some((command, argument)) if ADDRESS_COMMANDS.contains(&command) && !argument.contains(' ') => {
+                (format!("$ {} ", command), argument, symbols.keys().cloned().collect())
+            }
+            Some(_) => return vec![]
+        },
+        None => match line.split_once(' ') {
+            None => (String::new(), line, VERBS.iter().map(|verb| verb.to_string()).chain(exits()).collect()),
+            Some((verb, argument)) => {
+                let options = match verb {
+                    "go"           => exits(),
+                    "take"         => items(&[location]),
+                    "drop" | "use" => items(&[INVENTORY]),
+                    "look"         => items(&[location, INVENTORY]),
+                    _              => vec![]
+                };
+
+                (format!("{} ", verb), argument, options)
+            }
+        }
+    };
+
+    options
+        .into_iter()
+        .filter(|option| option.starts_with(partial))
+        .map(|option| format!("{}{}", prefix, option))
+        .collect()
+}
@@ -0,0 +1,444 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt::{self, Display, Formatter}};
+use crate::{ir::{literal, Expr, Function, Op, Stmt}, Number, Register};
+
+/*
+Dataflow passes over the IR of a function:
+
+    Liveness:             registers that may be read before being written, per block
+    Reaching definitions: register definitions that may reach each block
+    Value propagation:    abstract interpretation tracking constants, the values registers had on entry and the stack
+
+Calls are described through function summaries: the registers read by the callee before writing them (inputs) and the
+registers whose value differs on return (writes). Registers saved and restored through push/pop pairs are neither inputs
+nor writes. Register-indirect calls use the union of the summaries of the targets they were resolved to, either through
+constant propagation or through traces of the VM. Summaries for a whole program are computed by iterating the passes
+until they reach a fixed point.
+*/
+
+pub const REGISTERS: Register = 8;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub inputs: BTreeSet<Register>,
+    pub writes: BTreeSet<Register>
+}
+
+impl Summary {
+    pub fn unknown() -> Self {
+        Self { inputs: (0 .. REGISTERS).collect(), writes: (0 .. REGISTERS).collect() }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "inputs [{}], writes [{}]", registers(&self.inputs), registers(&self.writes))
+    }
+}
+
+pub type CallTargets = BTreeMap<u16, BTreeSet<u16>>;
+
+#[derive(Clone, Debug, Default)]
+pub struct Summaries {
+    pub functions: BTreeMap<u16, Summary>,
+    pub call_targets: CallTargets
+}
+
+impl Summaries {
+    pub fn call(&self, site: u16, target: &Number) -> Summary {
+        let targets = match literal(target) {
+            Some(target) => BTreeSet::from([target]),
+            None         => self.call_targets.get(&site).cloned().unwrap_or_default()
+        };
+
+        if targets.is_empty() {
+            return Summary::unknown();
+        }
+
+        targets
+            .iter()
+            .map(|target| self.functions.get(target).cloned().unwrap_or_else(Summary::unknown))
+            .fold(Summary::default(), |mut acc, summary| {
+                acc.inputs.extend(summary.inputs);
+                acc.writes.extend(summary.writes);
+                acc
+            })
+    }
+}
+
+pub fn register_uses(stmt: &Stmt, summaries: &Summaries) -> BTreeSet<Register> {
+    let mut uses: BTreeSet<Register> = stmt.op.register_uses().into_iter().collect();
+
+    if let Op::Call(target) = &stmt.op {
+        uses.extend(summaries.call(stmt.address, target).inputs);
+    }
+
+    uses
+}
+
+pub fn register_defs(stmt: &Stmt, summaries: &Summaries) -> BTreeSet<Register> {
+    match &stmt.op {
+        Op::Call(target) => summaries.call(stmt.address, target).writes,
+        op               => op.register_def().into_iter().collect()
+    }
+}
+
+pub struct Liveness {
+    pub live_in: BTreeMap<u16, BTreeSet<Register>>,
+    pub live_out: BTreeMap<u16, BTreeSet<Register>>
+}
+
+impl Liveness {
+    // Pushes in `ignored_uses` don't count as uses, which allows to skip register saves
+    pub fn compute(function: &Function, summaries: &Summaries, ignored_uses: &BTreeSet<u16>) -> Self {
+        let mut live_in: BTreeMap<u16, BTreeSet<Register>> = function.blocks.keys().map(|&b| (b, BTreeSet::new())).collect();
+        let mut live_out = live_in.clone();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for block in function.blocks.values().rev() {
+                let out: BTreeSet<Register> = block.successors.iter().flat_map(|s| live_in[s].clone()).collect();
+                let mut live = out.clone();
+
+                for stmt in block.stmts.iter().rev() {
+                    for def in register_defs(stmt, summaries) {
+                        live.remove(&def);
+                    }
+
+                    if !ignored_uses.contains(&stmt.address) {
+                        live.extend(register_uses(stmt, summaries));
+                    }
+                }
+
+                changed |= live_in[&block.start] != live || live_out[&block.start] != out;
+                live_in.insert(block.start, live);
+                live_out.insert(block.start, out);
+            }
+        }
+
+        Self { live_in, live_out }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Definition {
+    pub register: Register,
+    pub address: Option<u16>
+}
+
+impl Display for Definition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.address {
+            Some(address) => write!(f, "r{}@{}", self.register, address),
+            None          => write!(f, "r{}@entry", self.register)
+        }
+    }
+}
+
+pub struct ReachingDefinitions {
+    pub reach_in: BTreeMap<u16, BTreeSet<Definition>>,
+    pub reach_out: BTreeMap<u16, BTreeSet<Definition>>
+}
+
+impl ReachingDefinitions {
+    pub fn compute(function: &Function, summaries: &Summaries) -> Self {
+        let predecessors = function.predecessors();
+        let entry_defs: BTreeSet<Definition> = (0 .. REGISTERS).map(|register| Definition { register, address: None }).collect();
+        let mut reach_in: BTreeMap<u16, BTreeSet<Definition>> = function.blocks.keys().map(|&b| (b, BTreeSet::new())).collect();
+        let mut reach_out = reach_in.clone();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for block in function.blocks.values() {
+                let mut reaching: BTreeSet<Definition> = predecessors[&block.start]
+                    .iter()
+                    .flat_map(|p| reach_out[p].clone())
+                    .collect();
+
+                if block.start == function.entry {
+                    reaching.extend(&entry_defs);
+                }
+
+                let block_in = reaching.clone();
+
+                for stmt in &block.stmts {
+                    for register in register_defs(stmt, summaries) {
+                        reaching.retain(|def| def.register != register);
+                        reaching.insert(Definition { register, address: Some(stmt.address) });
+                    }
+                }
+
+                changed |= reach_in[&block.start] != block_in || reach_out[&block.start] != reaching;
+                reach_in.insert(block.start, block_in);
+                reach_out.insert(block.start, reaching);
+            }
+        }
+
+        Self { reach_in, reach_out }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AbstractValue {
+    Const(u16),
+    Entry(Register),
+    Unknown
+}
+
+impl AbstractValue {
+    fn join(&self, other: &Self) -> Self {
+        if self == other { *self } else { AbstractValue::Unknown }
+    }
+}
+
+impl Display for AbstractValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AbstractValue::Const(x) => write!(f, "{}", x),
+            AbstractValue::Entry(x) => write!(f, "r{}@entry", x),
+            AbstractValue::Unknown  => write!(f, "?")
+        }
+    }
+}
+
+// Stack slots keep the address of the push that produced them, if it's the same on every path
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    pub registers: [AbstractValue; REGISTERS],
+    pub stack: Option<Vec<(AbstractValue, Option<u16>)>>
+}
+
+impl State {
+    fn entry() -> Self {
+        Self { registers: std::array::from_fn(AbstractValue::Entry), stack: Some(vec![]) }
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        let registers = std::array::from_fn(|r| self.registers[r].join(&other.registers[r]));
+        let stack = match (&self.stack, &other.stack) {
+            (Some(a), Some(b)) if a.len() == b.len() => Some(
+                a.iter()
+                    .zip(b)
+                    .map(|((va, sa), (vb, sb))| (va.join(vb), if sa == sb { *sa } else { None }))
+                    .collect()
+            ),
+            _ => None
+        };
+
+        Self { registers, stack }
+    }
+
+    pub fn value(&self, number: &Number) -> AbstractValue {
+        match number {
+            Number::Literal(x)  => AbstractValue::Const(*x),
+            Number::Register(x) => self.registers[*x]
+        }
+    }
+}
+
+pub struct Values {
+    pub before: BTreeMap<u16, State>,
+    pub saves: BTreeSet<u16>,
+    pub writes: BTreeSet<Register>
+}
+
+impl Values {
+    pub fn compute(function: &Function, summaries: &Summaries) -> Self {
+        let mut block_states = BTreeMap::from([(function.entry, State::entry())]);
+        let mut before = BTreeMap::new();
+        let mut pending = vec![function.entry];
+        let mut consumers: BTreeMap<u16, BTreeSet<Register>> = BTreeMap::new();
+        let mut unknown_consumer = false;
+        let mut writes = BTreeSet::new();
+
+        while let Some(start) = pending.pop() {
+            let block = &function.blocks[&start];
+            let mut state = block_states[&start].clone();
+
+            for stmt in &block.stmts {
+                before.insert(stmt.address, state.clone());
+
+                match &stmt.op {
+                    Op::Assign(a, Expr::Pop) => {
+                        state.registers[*a] = match state.stack.as_mut().and_then(|stack| stack.pop()) {
+                            Some((value, Some(site))) => {
+                                consumers.entry(site).or_default().insert(*a);
+                                value
+                            }
+                            Some((value, None)) => {
+                                unknown_consumer = true;
+                                value
+                            }
+                            None => {
+                                unknown_consumer = true;
+                                AbstractValue::Unknown
+                            }
+                        };
+                    }
+                    Op::Assign(a, expr) => {
+                        state.registers[*a] = match expr {
+                            Expr::Binary(op, b, c) => match (state.value(b), state.value(c)) {
+                                (AbstractValue::Const(b), AbstractValue::Const(c)) => op.eval(b, c).map_or(AbstractValue::Unknown, AbstractValue::Const),
+                                _                                                  => AbstractValue::Unknown
+                            },
+                            Expr::Not(b) => match state.value(b) {
+                                AbstractValue::Const(b) => AbstractValue::Const(!b % 32768),
+                                _                       => AbstractValue::Unknown
+                            },
+                            Expr::Value(b) => state.value(b),
+                            _              => AbstractValue::Unknown
+                        };
+                    }
+                    Op::Call(target) => {
+                        for register in summaries.call(stmt.address, target).writes {
+                            state.registers[register] = AbstractValue::Unknown;
+                        }
+                    }
+                    Op::Push(a) => {
+                        let value = state.value(a);
+
+                        if let Some(stack) = state.stack.as_mut() {
+                            stack.push((value, Some(stmt.address)));
+                        }
+                    }
+                    Op::Return => {
+                        writes.extend((0 .. REGISTERS).filter(|&r| state.registers[r] != AbstractValue::Entry(r)));
+                    }
+                    _ => ()
+                }
+            }
+
+            for successor in &block.successors {
+                let joined = match block_states.get(successor) {
+                    Some(previous) => previous.join(&state),
+                    None           => state.clone()
+                };
+
+                if block_states.get(successor) != Some(&joined) {
+                    block_states.insert(*successor, joined);
+                    pending.push(*successor);
+                }
+            }
+        }
+
+        let saves = if unknown_consumer {
+            BTreeSet::new()
+        } else {
+            function.stmts()
+                .filter_map(|stmt| match (&stmt.op, before.get(&stmt.address)) {
+                    (Op::Push(Number::Register(r)), Some(state)) if state.registers[*r] == AbstractValue::Entry(*r) => {
+                        (consumers.get(&stmt.address) == Some(&BTreeSet::from([*r]))).then_some(stmt.address)
+                    }
+                    _ => None
+                })
+                .collect()
+        };
+
+        Self { before, saves, writes }
+    }
+}
+
+pub fn summarize(function: &Function, summaries: &Summaries) -> Summary {
+    let values = Values::compute(function, summaries);
+    let liveness = Liveness::compute(function, summaries, &values.saves);
+
+    Summary { inputs: liveness.live_in[&function.entry].clone(), writes: values.writes }
+}
+
+pub struct Program {
+    pub functions: BTreeMap<u16, Function>,
+    pub summaries: Summaries
+}
+
+impl Program {
+    pub fn lift(memory: &[u16], roots: &[u16]) -> Self {
+        Self::lift_with_traces(memory, roots, &CallTargets::new())
+    }
+
+    // Discovers functions from the roots through direct calls, register-indirect calls resolved by constant propagation
+    // and the traced targets of each call site, until no more targets are found
+    pub fn lift_with_traces(memory: &[u16], roots: &[u16], traced: &CallTargets) -> Self {
+        let mut program = Self { functions: BTreeMap::new(), summaries: Summaries::default() };
+        let mut pending = roots.to_vec();
+
+        loop {
+            while let Some(entry) = pending.pop() {
+                if program.functions.contains_key(&entry) || entry as usize >= memory.len() {
+                    continue;
+                }
+
+                program.functions.insert(entry, Function::lift(memory, entry));
+                pending.extend(program.resolve_calls(entry, traced));
+            }
+
+            program.compute_summaries();
+
+            for entry in program.functions.keys().copied().collect::<Vec<u16>>() {
+                pending.extend(program.resolve_calls(entry, traced));
+            }
+
+            pending.retain(|target| !program.functions.contains_key(target));
+
+            if pending.is_empty() {
+                return program;
+            }
+        }
+    }
+
+    fn resolve_calls(&mut self, entry: u16, traced: &CallTargets) -> Vec<u16> {
+        let function = &self.functions[&entry];
+        let values = Values::compute(function, &self.summaries);
+        let mut targets = vec![];
+
+        for (site, target) in function.call_sites() {
+            match target {
+                Number::Literal(target) => targets.push(target),
+                Number::Register(r)     => {
+                    let resolved = self.summaries.call_targets.entry(site).or_default();
+
+                    if let Some(AbstractValue::Const(target)) = values.before.get(&site).map(|state| state.registers[r]) {
+                        resolved.insert(target);
+                    }
+
+                    resolved.extend(traced.get(&site).into_iter().flatten());
+                    targets.extend(resolved.iter());
+                }
+            }
+        }
+
+        targets
+    }
+
+    // Writes only grow as callee writes grow, so they are computed first. Inputs are then grown from empty sets.
+    fn compute_summaries(&mut self) {
+        self.summaries.functions = self.functions.keys().map(|&entry| (entry, Summary::default())).collect();
+
+        for compute_inputs in [false, true] {
+            let mut changed = true;
+
+            while changed {
+                changed = false;
+
+                for (entry, function) in &self.functions {
+                    let mut summary = summarize(function, &self.summaries);
+
+                    if !compute_inputs {
+                        summary.inputs.clear();
+                    }
+
+                    if self.summaries.functions[entry] != summary {
+                        self.summaries.functions.insert(*entry, summary);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn registers(registers: &BTreeSet<Register>) -> String {
+    registers.iter().map(|r| format!("r{}", r)).collect::<Vec<String>>().join(" ")
+}
@@ -0,0 +1,324 @@
+use std::{collections::BTreeMap, fmt::Write};
+use crate::{
+    asm::Listing,
+    codes::Codes,
+    discovery::Layout,
+    patch::Patch,
+    world::{get_current_location, get_inventory, get_items, get_place, get_string, is_readable, move_item, move_player, World, INVENTORY},
+    Instruction,
+    MAX_SIZE,
+    VM
+};
+
+/*
+The debugger runs the VM reading its commands line by line, passing the lines which aren't debugger commands to the game
+as its input:
+
+    $ add_breakpoint <address>          stops the VM before running the instruction at the address
+    $ remove_breakpoint <address>       removes the breakpoint at the address
+    $ disassemble <address> <count>     prints the given number of instructions from the address
+    $ set_memory <address> <value>      writes the value to the memory address
+    $ set_register <register> <value>   writes the value to the register (0 to 7)
+    $ patch <file>                      applies the patch file to the memory (see patch.rs for its format)
+    $ continue                          resumes the VM after a breakpoint
+    $ step                              runs the instruction at pc
+    $ next                              runs the instruction at pc, or the whole function if it's a call
+    $ codes                             prints the codes announced so far
+    $ symbols                           prints the named addresses
+    $ where                             prints the current location
+    $ inventory                         prints the items in the inventory
+    $ items                             prints every item along with where it is
+    $ goto <location>                   moves the player to the location, whose enter fn runs with the next command
+    $ give <item>                       moves the item to the inventory
+    $ place <item> <location>           moves the item to the location
+    $ exit                              stops the debugger
+
+The game state is read and written in the memory at the addresses of the layout set on the debugger (the ones of the
+original binary by default), without running the VM, so it can be inspected and changed while stopped anywhere. Locations
+and items are given by their id or name, which may have several words, and names are looked up in the game world.
+
+Addresses can also be given by the names of the symbols set on the debugger (see discovery.rs for the ones of the game).
+
+The output of the VM is captured instead of printed and returned along with the output of the debugger commands, so the
+codes announced by the game are collected along the way and the debugger can be driven headless.
+
+When an interrupt check is set (such as a key being pressed in the terminal debugger), the VM runs in slices of steps
+and stops once the check is true between two slices, so long computations like the teleporter check can be interrupted.
+*/
+
+pub const COMMANDS: [&str; 18] = [
+    "add_breakpoint", "remove_breakpoint", "disassemble", "set_memory", "set_register", "patch", "continue", "step", "next",
+    "codes", "symbols", "where", "inventory", "items", "goto", "give", "place", "exit"
+];
+pub const ADDRESS_COMMANDS: [&str; 4] = ["add_breakpoint", "remove_breakpoint", "disassemble", "set_memory"];
+
+const SLICE_STEPS: usize = 100_000;
+const UNREADABLE: &str = "the game state is unreadable until the game starts and decrypts its strings";
+
+pub struct Debugger {
+    pub vm: VM,
+    pub codes: Codes,
+    pub symbols: BTreeMap<String, u16>,
+    pub layout: Layout,
+    pub interrupt: Option<Box<dyn FnMut() -> bool>>
+}
+
+fn disassembly(vm: &VM, position: u16, count: usize) -> String {
+    Listing::new(vm.dbg_get_memory())
+        .raw_words(true)
+        .lines_from(position)
+        .take(count)
+        .fold(String::new(), |mut output, line| {
+            writeln!(output, "{}", line).unwrap();
+            output
+        })
+}
+
+// Number typed for a command, which should be below the limit
+fn parse(word: &str, name: &str, limit: usize) -> Result<usize, String> {
+    word.parse().ok().filter(|&number| number < limit).ok_or_else(|| format!("{} should be {} below {}", word, name, limit))
+}
+
+fn report(result: Result<String, String>) -> String {
+    result.unwrap_or_else(|error| format!("{}\n", error))
+}
+
+impl Debugger {
+    pub fn new(mut vm: VM) -> Self {
+        vm.dbg_set_output_enabled(false);
+        vm.dbg_set_output_capture(true);
+
+        Self { vm, codes: Codes::default(), symbols: BTreeMap::new(), layout: Layout::default(), interrupt: None }
+    }
+
+    pub fn layout(self, layout: Layout) -> Self {
+        Self { layout, ..self }
+    }
+
+    pub fn symbols(self, symbols: BTreeMap<String, u16>) -> Self {
+        Self { symbols, ..self }
+    }
+
+    // Executes a line of input, returning what got printed or nothing once the debugger should stop
+    pub fn execute(&mut self, input: &str) -> Option<String> {
+        let words: Vec<String> = input
+            .split_whitespace()
+            .map(|word| self.symbols.get(word).map_or(word.to_string(), |address| address.to_string()))
+            .collect();
+
+        let output = match words.iter().map(String::as_str).collect::<Vec<&str>>()[..] {
+            ["$", "add_breakpoint", position]      => report(self.add_breakpoint(position)),
+            ["$", "remove_breakpoint", position]   => report(self.remove_breakpoint(position)),
+            ["$", "disassemble", position, count]  => report(self.disassemble(position, count)),
+            ["$", "set_memory", position, value]   => report(self.set_memory(position, value)),
+            ["$", "set_register", register, value] => report(self.set_register(register, value)),
+            ["$", "patch", file_path]              => self.patch(file_path),
+            ["$", "continue"]                      => self.run(),
+            ["$", "step"]                          => self.step(),
+            ["$", "next"]                          => self.next(),
+            ["$", "codes"]                         => self.codes.to_table(),
+            ["$", "symbols"]                       => self.symbols.iter().map(|(name, address)| format!("{}: {}\n", name, address)).collect(),
+            ["$", "where"]                         => self.location(),
+            ["$", "inventory"]                     => self.inventory(),
+            ["$", "items"]                         => self.items(),
+            ["$", "goto", _, ..]                   => self.goto(&words[2 ..].join(" ")),
+            ["$", "give", _, ..]                   => self.give(&words[2 ..].join(" ")),
+            ["$", "place", _, _, ..]               => self.place(&words[2 ..]),
+            ["$", "exit"]                          => return None,
+            _ => {
+                self.vm.input_command(input);
+                self.run()
+            }
+        };
+
+        Some(output)
+    }
+
+    fn add_breakpoint(&mut self, position: &str) -> Result<String, String> {
+        self.vm.dbg_add_breakpoint(parse(position, "an address", MAX_SIZE)? as u16);
+        Ok(String::new())
+    }
+
+    fn remove_breakpoint(&mut self, position: &str) -> Result<String, String> {
+        self.vm.dbg_remove_breakpoint(parse(position, "an address", MAX_SIZE)? as u16);
+        Ok(String::new())
+    }
+
+    fn disassemble(&self, position: &str, count: &str) -> Result<String, String> {
+        Ok(disassembly(&self.vm, parse(position, "an address", MAX_SIZE)? as u16, parse(count, "a count", MAX_SIZE)?))
+    }
+
+    // Memory words may also refer to the registers
+    fn set_memory(&mut self, position: &str, value: &str) -> Result<String, String> {
+        self.vm.dbg_set_memory(parse(position, "an address", MAX_SIZE)?, parse(value, "a value", MAX_SIZE + 8)? as u16);
+        Ok(String::new())
+    }
+
+    fn set_register(&mut self, register: &str, value: &str) -> Result<String, String> {
+        self.vm.dbg_set_register(parse(register, "a register", 8)?, parse(value, "a value", MAX_SIZE)? as u16);
+        Ok(String::new())
+    }
+
+    fn patch(&mut self, file_path: &str) -> String {
+        match Patch::read(file_path).and_then(|patch| self.vm.apply_patch(&patch).map(|_| patch.changes.len())) {
+            Ok(changed) => format!("patch {} applied ({} words changed)\n", file_path, changed),
+            Err(error)  => format!("{}\n", error)
+        }
+    }
+
+    // Reads the game state from the memory once the game has decrypted its strings
+    fn game_state(&self, read: impl Fn(&[u16], &Layout) -> String) -> String {
+        let memory = self.vm.dbg_get_memory();
+
+        match is_readable(memory, self.layout.current_location) {
+            true  => read(memory, &self.layout),
+            false => format!("{}\n", UNREADABLE)
+        }
+    }
+
+    fn location(&self) -> String {
+        self.game_state(|memory, layout| {
+            let location = get_current_location(memory, layout.current_location);
+
+            format!("{} ({})\n", location.name, location.id)
+        })
+    }
+
+    fn inventory(&self) -> String {
+        self.game_state(|memory, layout| match get_inventory(memory, layout.items_table)[..] {
+            []        => "the inventory is empty\n".to_string(),
+            ref items => items.iter().map(|item| format!("{} ({})\n", item.name, item.id)).collect()
+        })
+    }
+
+    fn items(&self) -> String {
+        self.game_state(|memory, layout| {
+            get_items(memory, layout.items_table)
+                .iter()
+                .map(|item| format!("{} ({}): {}\n", item.name, item.id, get_place(memory, item.location)))
+                .collect()
+        })
+    }
+
+    fn world(&self) -> Result<World, String> {
+        match is_readable(self.vm.dbg_get_memory(), self.layout.current_location) {
+            true  => Ok(self.layout.read_world(self.vm.dbg_get_memory())),
+            false => Err(UNREADABLE.to_string())
+        }
+    }
+
+    fn goto(&mut self, name: &str) -> String {
+        let location = self.world().and_then(|world| world.find_location(name).map(|location| location.id));
+
+        match location {
+            Ok(location) => {
+                move_player(&mut self.vm, self.layout.current_location, location);
+                format!("moved to {}\n", get_place(self.vm.dbg_get_memory(), location))
+            }
+            Err(error) => format!("{}\n", error)
+        }
+    }
+
+    fn move_item(&mut self, item: usize, location: usize) -> String {
+        let memory = self.vm.dbg_get_memory();
+        let moved = format!("{} ({}) moved to {}\n", get_string(memory, memory[item]), item, get_place(memory, location));
+
+        move_item(&mut self.vm, item, location);
+        moved
+    }
+
+    fn give(&mut self, name: &str) -> String {
+        match self.world().and_then(|world| world.find_item(name).map(|item| item.id)) {
+            Ok(item)   => self.move_item(item, INVENTORY),
+            Err(error) => format!("{}\n", error)
+        }
+    }
+
+    // The item is the shortest name at the start of the words, and the location the rest of them
+    fn place(&mut self, words: &[String]) -> String {
+        let found = self.world().and_then(|world| {
+            let size = (1 .. words.len())
+                .find(|&size| !world.items_named(&words[.. size].join(" ")).is_empty())
+                .ok_or_else(|| format!("no item is at the start of {}", words.join(" ")))?;
+
+            Ok((world.find_item(&words[.. size].join(" "))?.id, world.find_location(&words[size ..].join(" "))?.id))
+        });
+
+        match found {
+            Ok((item, location)) => self.move_item(item, location),
+            Err(error)           => format!("{}\n", error)
+        }
+    }
+
+    fn step(&mut self) -> String {
+        self.run_steps(Some(1))
+    }
+
+    // Runs until the call at pc returns, through a breakpoint at the return address which is removed unless it was set
+    fn next(&mut self) -> String {
+        let pc = self.vm.dbg_get_pc();
+        let Instruction::FunctionCall(_) = Instruction::decode(self.vm.dbg_get_memory(), pc) else { return self.step() };
+        let (return_address, depth) = (pc + 2, self.vm.dbg_get_stack().len());
+        let temporary = !self.vm.dbg_get_breakpoints().contains(&return_address);
+        let mut output = String::new();
+
+        self.vm.dbg_add_breakpoint(return_address);
+
+        // Recursive calls get back to the return address deeper in the stack
+        loop {
+            output.push_str(&self.run());
+
+            if self.vm.is_halted() || self.vm.dbg_get_pc() != return_address || self.vm.dbg_get_stack().len() <= depth {
+                break;
+            }
+        }
+
+        if temporary {
+            self.vm.dbg_remove_breakpoint(return_address);
+        }
+
+        output
+    }
+
+    fn run(&mut self) -> String {
+        self.run_steps(None)
+    }
+
+    // Runs the given number of steps or without limit, in slices checking the interrupt in between if it's set
+    fn run_steps(&mut self, steps: Option<usize>) -> String {
+        let mut steps_left = steps;
+        let mut output = String::new();
+
+        loop {
+            let slice = match self.interrupt {
+                Some(_) => Some(steps_left.map_or(SLICE_STEPS, |steps| steps.min(SLICE_STEPS))),
+                None    => steps_left
+            };
+
+            self.vm.dbg_set_step_limit(slice);
+            self.vm.run();
+            output.push_str(&self.vm.dbg_take_captured_output());
+
+            // The VM stopped only because the slice ran out when it's not at a breakpoint either
+            let sliced = slice.is_some()
+                && self.vm.dbg_get_steps_left() == Some(0)
+                && !self.vm.is_halted()
+                && !self.vm.dbg_get_breakpoints().contains(&self.vm.dbg_get_pc());
+
+            steps_left = steps_left.zip(slice).map(|(steps, slice)| steps - slice);
+
+            if !sliced || steps_left == Some(0) {
+                break;
+            }
+
+            if self.interrupt.as_mut().is_some_and(|interrupt| interrupt()) {
+                writeln!(output, "interrupted at {}", self.vm.dbg_get_pc()).unwrap();
+                break;
+            }
+        }
+
+        self.vm.dbg_set_step_limit(None);
+        self.codes.watch(&output);
+        output
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::{
+    dataflow::Program,
+    handlers::Handler,
+    ir::{Function, Op},
+    route::Planner,
+    world::{get_items, get_locations, is_string, World},
+    Instruction,
+    Number,
+    VM
+};
+
+/*
+Addresses of the game data and routines differ between copies of the challenge binary, so they are found by signature
+on the memory image of the game once it's waiting for the first command:
+
+    Current location:  pointer read by the adventure loop to call the enter fn of the location
+                       (rmem a L; add a a 4; rmem a a; jf a _; call a)
+    Adventure loop:    closest target of a jmp or call preceding the enter fn call and aligned with it
+    Items table:       largest array of pointers to records whose name and description are valid strings, and whose
+                       location is either the inventory, nowhere or a record whose name is a valid string
+    Start location:    value of the current location pointer when the first room has been printed
+    Teleporter check:  call to a recursive function whose result is compared (call f; eq b r0 k; jf b _), along with
+                       the arguments set just before the call and the noops preceding them, where a jump to the
+                       success branch can be patched
+    Teleport location: initial location of the item whose use fn contains the teleporter check
+    Synacor/Beach:     locations written by the teleporter use fn when skipping the check or after passing it
+    Forced maze:       location whose enter fn unconditionally writes the current location pointer
+    End location:      farthest location from the start one
+
+Anything that can't be found falls back to the address in the original challenge binary and is reported as such.
+
+The enter and use fns of the world also tell the route planner which items are needed on the way: a location whose
+enter fn moves the player away depending on a call with an item (such as 6012(r0=2702), the lit lantern in the dark
+passages) is guarded by that item, and a use fn writing the location of items moves them, needing the items whose
+location it reads (such as the can reading the one of the empty lantern).
+
+The addresses are also named as symbols for the debugger, along with the records of the items and the enter and use fns
+of the world, named after their location or item (`item_tablet`, `enter_vault_door`, `use_teleporter`).
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TeleporterCheck {
+    pub bypass: u16,
+    pub call: u16,
+    pub function: u16,
+    pub arguments: [u16; 2],
+    pub comparison: u16,
+    pub expected: u16,
+    pub success: u16,
+    pub failure: u16,
+    pub use_fn: u16
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub adventure_loop: u16,
+    pub current_location: u16,
+    pub items_table: usize,
+    pub start_location: usize,
+    pub end_location: usize,
+    pub forced_maze_from: usize,
+    pub forced_maze_to: usize,
+    pub teleport_location: usize,
+    pub synacor_location: usize,
+    pub beach_location: usize,
+    pub teleporter: TeleporterCheck,
+    pub fallbacks: Vec<&'static str>
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            adventure_loop: 2756,
+            current_location: 2754,
+            items_table: 27395,
+            start_location: 2339,
+            end_location: 2665,
+            forced_maze_from: 2434,
+            forced_maze_to: 2439,
+            teleport_location: 2485,
+            synacor_location: 2510,
+            beach_location: 2520,
+            teleporter: TeleporterCheck {
+                bypass: 5500,
+                call: 5511,
+                function: 6049,
+                arguments: [4, 1],
+                comparison: 5513,
+                expected: 6,
+                success: 5520,
+                failure: 5601,
+                use_fn: 5467
+            },
+            fallbacks: vec![]
+        }
+    }
+}
+
+fn sequence(memory: &[u16], address: usize, count: usize) -> Vec<Instruction> {
+    let mut address = address as u16;
+
+    (0 .. count)
+        .map(|_| {
+            let instruction = Instruction::decode(memory, address);
+
+            address += instruction.size();
+            instruction
+        })
+        .collect()
+}
+
+fn find_enter_fn_call(memory: &[u16]) -> Option<(u16, u16)> {
+    (0 .. memory.len()).find_map(|address| match sequence(memory, address, 5)[..] {
+        [
+            Instruction::MemoryRead(a, Number::Literal(location)),
+            Instruction::Add(b, Number::Register(c), Number::Literal(4)),
+            Instruction::MemoryRead(d, Number::Register(e)),
+            Instruction::JumpIfFalse(Number::Register(f), _),
+            Instruction::FunctionCall(Number::Register(g))
+        ] if [b, c, d, e, f, g].iter().all(|&r| r == a) => Some((address as u16, location)),
+        _ => None
+    })
+}
+
+fn find_adventure_loop(memory: &[u16], enter_fn_call: u16) -> Option<u16> {
+    let is_aligned = |mut address: u16| {
+        while address < enter_fn_call {
+            address += Instruction::decode(memory, address).size();
+        }
+
+        address == enter_fn_call
+    };
+
+    memory
+        .windows(2)
+        .filter(|words| matches!(words[0], 6 | 17) && words[1] <= enter_fn_call && enter_fn_call - words[1] < 256)
+        .map(|words| words[1])
+        .filter(|&address| is_aligned(address))
+        .max()
+}
+
+fn find_items_table(memory: &[u16]) -> Option<usize> {
+    (0 .. memory.len())
+        .filter(|&position| {
+            let size = memory[position] as usize;
+
+            (2 ..= 256).contains(&size) && position + size < memory.len() && memory[position + 1 ..= position + size]
+                .iter()
+                .all(|&item| (item as usize) + 3 < memory.len()
+                    && is_string(memory, memory[item as usize])
+                    && is_string(memory, memory[item as usize + 1])
+                    && match memory[item as usize + 2] {
+                        0 | 32767 => true,
+                        location  => memory.get(location as usize).is_some_and(|&name| is_string(memory, name))
+                    })
+        })
+        .max_by_key(|&position| memory[position])
+}
+
+fn find_teleporter_check(memory: &[u16]) -> Option<TeleporterCheck> {
+    (6 .. memory.len()).find_map(|address| {
+        let [
+            Instruction::SetRegister(0, Number::Literal(r0)),
+            Instruction::SetRegister(1, Number::Literal(r1)),
+            Instruction::FunctionCall(Number::Literal(function)),
+            Instruction::CompareEquals(a, Number::Register(0), Number::Literal(expected)),
+            Instruction::JumpIfFalse(Number::Register(b), Number::Literal(failure))
+        ] = sequence(memory, address - 6, 5)[..] else { return None };
+        let recursive = Function::lift(memory, function)
+            .call_sites()
+            .iter()
+            .any(|(_, target)| *target == Number::Literal(function));
+
+        let noops = memory[.. address - 6].iter().rev().take_while(|&&word| word == 21).count();
+
+        (a == b && recursive).then_some(TeleporterCheck {
+            bypass: (address - 6 - noops) as u16,
+            call: address as u16,
+            function,
+            arguments: [r0, r1],
+            comparison: address as u16 + 2,
+            expected,
+            success: address as u16 + 9,
+            failure,
+            use_fn: 0
+        })
+    })
+}
+
+fn location_stores(memory: &[u16], function: u16, current_location: u16) -> Vec<(u16, usize, bool)> {
+    let function = Function::lift(memory, function);
+
+    function.blocks
+        .values()
+        .flat_map(|block| block.stmts.iter().map(move |stmt| (block.start, stmt)))
+        .filter_map(|(block, stmt)| match stmt.op {
+            Op::Store(Number::Literal(a), Number::Literal(b)) if a == current_location => {
+                Some((stmt.address, b as usize, block == function.entry))
+            }
+            _ => None
+        })
+        .collect()
+}
+
+// Name made of lowercase words joined by underscores
+fn symbol(prefix: &str, name: &str) -> String {
+    let words: Vec<String> = name.split(|c: char| !c.is_ascii_alphanumeric()).filter(|word| !word.is_empty()).map(str::to_lowercase).collect();
+
+    format!("{}_{}", prefix, words.join("_"))
+}
+
+impl Layout {
+    // Named addresses, keeping the first location or item of the ones sharing a name
+    pub fn symbols(&self, world: &World) -> BTreeMap<String, u16> {
+        let mut symbols = BTreeMap::from([
+            ("adventure_loop".to_string(), self.adventure_loop),
+            ("current_location".to_string(), self.current_location),
+            ("items_table".to_string(), self.items_table as u16),
+            ("teleporter_check".to_string(), self.teleporter.function),
+            ("teleporter_call".to_string(), self.teleporter.call),
+            ("teleporter_bypass".to_string(), self.teleporter.bypass)
+        ]);
+        let handlers = world.locations
+            .iter()
+            .map(|location| (symbol("enter", &location.name), location.enter_fn))
+            .chain(world.items.iter().map(|item| (symbol("use", &item.name), item.use_fn)))
+            .filter(|&(_, handler)| handler != 0);
+        let items = world.items.iter().map(|item| (symbol("item", &item.name), item.id as u16));
+
+        for (name, address) in handlers.chain(items) {
+            symbols.entry(name).or_insert(address);
+        }
+
+        symbols
+    }
+
+    // World reachable from the start location, including the moves done by the enter fns of the locations and the use fns
+    // of the items (from their initial location) as synthetic exits
+    pub fn read_world(&self, memory: &[u16]) -> World {
+        let mut roots = vec![self.start_location];
+
+        loop {
+            let mut world = World::read(memory, self.items_table, &roots);
+            let mut moves: Vec<(usize, String, usize)> = vec![];
+            let handlers: Vec<(usize, u16, Option<String>)> = world.locations
+                .iter()
+                .map(|location| (location.id, location.enter_fn, None))
+                .chain(world.items.iter().map(|item| (item.location, item.use_fn, Some(format!("use {}", item.name)))))
+                .filter(|&(location, handler, _)| handler != 0 && world.location(location).is_some())
+                .collect();
+            let program = Program::lift(memory, &handlers.iter().map(|&(_, handler, _)| handler).collect::<Vec<u16>>());
+
+            for (location, handler, command) in handlers {
+                for movement in Handler::analyze(&program, handler, self.current_location).moves {
+                    moves.push((location, movement.label(command.as_deref()), movement.target));
+                }
+            }
+
+            let mut missing: Vec<usize> = moves
+                .iter()
+                .map(|&(_, _, target)| target)
+                .filter(|&target| world.location(target).is_none())
+                .collect();
+
+            missing.sort();
+            missing.dedup();
+
+            if missing.is_empty() {
+                for (from, label, to) in moves {
+                    world.add_synthetic_exit(from, &label, to);
+                }
+
+                return world;
+            }
+
+            roots.extend(missing);
+        }
+    }
+
+    // Route planner on the world, knowing the forced maze move and the guards and uses of items found on the handlers
+    pub fn planner<'a>(&self, memory: &[u16], world: &'a World) -> Planner<'a> {
+        let enter_fns = world.locations.iter().map(|location| location.enter_fn);
+        let use_fns = world.items.iter().map(|item| item.use_fn);
+        let program = Program::lift(memory, &enter_fns.chain(use_fns).filter(|&handler| handler != 0).collect::<Vec<u16>>());
+        let analyze = |handler: u16| Handler::analyze(&program, handler, self.current_location);
+        let item_at = |address: u16| world.items.iter().find(|item| item.id + 2 == address as usize).map(|item| item.id);
+        let mut planner = Planner::new(world).forced_move(self.forced_maze_from, self.forced_maze_to);
+
+        for location in world.locations.iter().filter(|location| location.enter_fn != 0) {
+            for movement in analyze(location.enter_fn).moves {
+                for &item in movement.arguments.iter().filter(|&&argument| world.item(argument as usize).is_some()) {
+                    planner = planner.guard(location.id, item as usize);
+                }
+            }
+        }
+
+        for item in world.items.iter().filter(|item| item.use_fn != 0) {
+            let handler = analyze(item.use_fn);
+            let needs: Vec<usize> = handler.reads.iter().filter_map(|&address| item_at(address)).collect();
+            let moves: Vec<(usize, usize)> = handler.writes
+                .iter()
+                .filter_map(|&(address, value)| Some((item_at(address)?, value? as usize)))
+                .collect();
+
+            if !moves.is_empty() {
+                planner = planner.item_use(item.id, &needs, &moves);
+            }
+        }
+
+        planner
+    }
+}
+
+fn farthest_location(memory: &[u16], layout: &Layout) -> Option<usize> {
+    let connections: HashMap<usize, Vec<usize>> = layout.read_world(memory)
+        .locations
+        .into_iter()
+        .map(|location| (location.id, location.connections.into_iter().map(|exit| exit.target).collect()))
+        .collect();
+
+    let mut distances = BTreeMap::from([(layout.start_location, 0)]);
+    let mut pending = VecDeque::from([layout.start_location]);
+
+    while let Some(location) = pending.pop_front() {
+        for &next in connections.get(&location).into_iter().flatten() {
+            if !distances.contains_key(&next) {
+                distances.insert(next, distances[&location] + 1);
+                pending.push_back(next);
+            }
+        }
+    }
+
+    distances.into_iter().max_by_key(|&(id, distance)| (distance, id)).map(|(id, _)| id)
+}
+
+// Runs a freshly loaded VM until it waits for the first command and finds the layout on its memory
+pub fn discover(vm: &mut VM) -> Layout {
+    let mut layout = Layout::default();
+
+    vm.run();
+
+    let memory = vm.dbg_get_memory();
+
+    match find_enter_fn_call(memory) {
+        Some((site, current_location)) => {
+            layout.current_location = current_location;
+            layout.start_location = memory[current_location as usize] as usize;
+
+            match find_adventure_loop(memory, site) {
+                Some(adventure_loop) => layout.adventure_loop = adventure_loop,
+                None                 => layout.fallbacks.push("adventure_loop")
+            }
+        }
+        None => layout.fallbacks.extend(["adventure_loop", "current_location", "start_location"])
+    }
+
+    match find_items_table(memory) {
+        Some(items_table) => layout.items_table = items_table,
+        None              => layout.fallbacks.push("items_table")
+    }
+
+    match find_teleporter_check(memory) {
+        Some(mut teleporter) => {
+            teleporter.use_fn = layout.teleporter.use_fn;
+
+            let items = get_items(memory, layout.items_table);
+            let item = items
+                .iter()
+                .filter(|item| item.use_fn != 0 && item.use_fn <= teleporter.call)
+                .max_by_key(|item| item.use_fn);
+
+            match item {
+                Some(item) => {
+                    let stores = location_stores(memory, item.use_fn, layout.current_location);
+                    let beach = stores.iter().find(|(address, ..)| (teleporter.success .. teleporter.failure).contains(address));
+                    let synacor = stores.iter().find(|(address, ..)| !(teleporter.success .. teleporter.failure).contains(address));
+
+                    teleporter.use_fn = item.use_fn;
+                    layout.teleport_location = item.location;
+
+                    match (beach, synacor) {
+                        (Some(beach), Some(synacor)) => (layout.beach_location, layout.synacor_location) = (beach.1, synacor.1),
+                        _                            => layout.fallbacks.extend(["beach_location", "synacor_location"])
+                    }
+                }
+                None => layout.fallbacks.extend(["teleport_location", "beach_location", "synacor_location"])
+            }
+
+            layout.teleporter = teleporter;
+        }
+        None => layout.fallbacks.extend(["teleporter", "teleport_location", "beach_location", "synacor_location"])
+    }
+
+    let forced_maze = get_locations(memory, &[layout.start_location])
+        .into_iter()
+        .filter(|location| location.enter_fn != 0)
+        .find_map(|location| location_stores(memory, location.enter_fn, layout.current_location)
+            .into_iter()
+            .find(|(_, _, unconditional)| *unconditional)
+            .map(|(_, to, _)| (location.id, to)));
+
+    match forced_maze {
+        Some((from, to)) => (layout.forced_maze_from, layout.forced_maze_to) = (from, to),
+        None             => layout.fallbacks.extend(["forced_maze_from", "forced_maze_to"])
+    }
+
+    match farthest_location(memory, &layout) {
+        Some(end_location) => layout.end_location = end_location,
+        None               => layout.fallbacks.push("end_location")
+    }
+
+    layout
+}
@@ -0,0 +1,196 @@
+use std::{fs::{self, OpenOptions}, io::{stdout, Write}};
+use crate::terminal::{read_keys, Key, RawMode};
+
+/*
+The line editor reads a line from the terminal in raw mode, redrawing it after every key:
+
+    left / right, home / end    moves the cursor (ctrl-a and ctrl-e also go home and end)
+    backspace / delete          deletes the char before / at the cursor
+    up / down                   goes through the history, keeping the line being written at its end
+    tab                         completes the line up to the cursor, listing the options if there are several
+    ctrl-c                      discards the line
+    ctrl-d                      ends the input on an empty line
+
+The line is returned without its trailing spaces, such as the one left after a completion.
+
+Lines are added to the history unless they repeat the last one, and appended to the history file if there's one, which
+is read back (its last lines) when the editor starts.
+*/
+
+const HISTORY_SIZE: usize = 1000;
+
+#[derive(Default)]
+pub struct Editor {
+    history: Vec<String>,
+    history_file: Option<String>
+}
+
+// Longest prefix shared by the options
+fn common_prefix(options: &[String]) -> String {
+    let first = &options[0];
+    let size = options[1 ..]
+        .iter()
+        .map(|option| first.chars().zip(option.chars()).take_while(|(a, b)| a == b).count())
+        .min()
+        .unwrap_or(first.chars().count());
+
+    first.chars().take(size).collect()
+}
+
+// Options listed from the word being completed, which starts after the last space of the completed line since the
+// options may normalize what was typed (such as the spaces after $)
+fn listed<'a>(options: &'a [String], completed: &str) -> Vec<&'a str> {
+    let words = completed.rfind(' ').map_or(0, |idx| idx + 1);
+
+    options.iter().map(|option| &option[words ..]).collect()
+}
+
+impl Editor {
+    pub fn history_file(self, file_path: &str) -> Self {
+        let history: Vec<String> = fs::read_to_string(file_path).unwrap_or_default().lines().map(String::from).collect();
+        let history = history[history.len().saturating_sub(HISTORY_SIZE) ..].to_vec();
+
+        Self { history, history_file: Some(file_path.to_string()) }
+    }
+
+    // Line typed after the prompt, or nothing once the input ends
+    pub fn read_line(&mut self, prompt: &str, complete: impl Fn(&str) -> Vec<String>) -> Result<Option<String>, String> {
+        let _raw_mode = RawMode::enable()?;
+        let mut line: Vec<char> = vec![];
+        let mut cursor = 0;
+        let mut entry = self.history.len();
+        let mut draft = vec![];
+
+        loop {
+            // The line is redrawn from the start of the row, then the cursor moves back to its position
+            print!("\r\x1b[K{}{}", prompt, line.iter().collect::<String>());
+
+            if cursor < line.len() {
+                print!("\x1b[{}D", line.len() - cursor);
+            }
+
+            stdout().flush().ok();
+
+            let keys = read_keys()?;
+
+            if keys.is_empty() {
+                return Ok(None);
+            }
+
+            for key in keys {
+                match key {
+                    Key::Enter => {
+                        let line = line.iter().collect::<String>().trim_end().to_string();
+
+                        print!("\r\n");
+                        self.add(&line);
+                        return Ok(Some(line));
+                    }
+                    Key::EndOfFile if line.is_empty() => {
+                        print!("\r\n");
+                        return Ok(None);
+                    }
+                    Key::Interrupt => {
+                        print!("^C\r\n");
+                        line.clear();
+                        cursor = 0;
+                    }
+                    Key::Char(c) => {
+                        line.insert(cursor, c);
+                        cursor += 1;
+                    }
+                    Key::Backspace if cursor > 0 => {
+                        cursor -= 1;
+                        line.remove(cursor);
+                    }
+                    Key::Delete if cursor < line.len() => { line.remove(cursor); }
+                    Key::Left                          => cursor = cursor.saturating_sub(1),
+                    Key::Right                         => cursor = (cursor + 1).min(line.len()),
+                    Key::Home                          => cursor = 0,
+                    Key::End                           => cursor = line.len(),
+                    Key::Up | Key::Down => {
+                        let next = match key {
+                            Key::Up => entry.checked_sub(1),
+                            _       => Some(entry + 1).filter(|&next| next <= self.history.len())
+                        };
+
+                        if let Some(next) = next {
+                            if entry == self.history.len() {
+                                draft = line.clone();
+                            }
+
+                            entry = next;
+                            line = self.history.get(entry).map_or(draft.clone(), |line| line.chars().collect());
+                            cursor = line.len();
+                        }
+                    }
+                    Key::Tab => {
+                        let start: String = line[.. cursor].iter().collect();
+                        let mut options = complete(&start);
+
+                        options.sort();
+                        options.dedup();
+
+                        if options.is_empty() {
+                            continue;
+                        }
+
+                        let completed = match &options[..] {
+                            [option] => format!("{} ", option),
+                            _        => common_prefix(&options)
+                        };
+
+                        // Several options are listed (from the word being completed) unless the line can still be extended
+                        if completed.chars().count() <= cursor && options.len() > 1 {
+                            print!("\r\n{}\r\n", listed(&options, &completed).join("  "));
+                        }
+
+                        let end: Vec<char> = line[cursor ..].to_vec();
+
+                        line = completed.chars().chain(end).collect();
+                        cursor = completed.chars().count();
+                    }
+                    _ => ()
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+
+        self.history.push(line.to_string());
+
+        if self.history.len() > HISTORY_SIZE {
+            self.history.remove(0);
+        }
+
+        if let Some(file_path) = &self.history_file {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(file_path) {
+                writeln!(file, "{}", line).ok();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::{completion::complete, discovery::Layout, MAX_SIZE};
+    use super::{common_prefix, listed};
+
+    #[test]
+    fn listing_commands_after_spaces() {
+        let mut options = complete("$      ", &[0; MAX_SIZE], &Layout::default(), &BTreeMap::new());
+
+        options.sort();
+
+        let completed = common_prefix(&options);
+
+        assert_eq!(completed, "$ ");
+        assert!(listed(&options, &completed).contains(&"exit"));
+        assert!(listed(&options, &completed).contains(&"next"));
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::{hash_map::DefaultHasher, HashMap, VecDeque}, hash::{Hash, Hasher}};
+use crate::{codes::extract_codes, VM};
+
+/*
+The explorer plays the game without any knowledge of its world: starting from a VM waiting for a command, it looks at
+the room and the inventory and tries every exit, every item in the room (take) and every item in the inventory (use),
+each one on its own clone of the VM. The resulting states are deduplicated by a hash of the memory, so the same location
+with the same items in the same places is explored once, and states where the game halted aren't expanded.
+
+The game keeps the last command typed in memory, so the words which differ after two commands that don't change the game
+(looking around and an unknown command) are left out of the hash.
+
+The exploration is breadth first and stops adding states once the budget is reached, in which case some transitions may
+lead to states that weren't explored.
+*/
+
+pub struct State {
+    pub id: usize,
+    pub location: usize,
+    pub room: String,
+    pub halted: bool
+}
+
+pub struct Transition {
+    pub from: usize,
+    pub command: String,
+    pub to: Option<usize>
+}
+
+pub struct Exploration {
+    pub states: Vec<State>,
+    pub transitions: Vec<Transition>,
+    pub codes: Vec<(usize, String)>,
+    pub complete: bool
+}
+
+pub struct Explorer {
+    current_location: u16,
+    budget: usize
+}
+
+const UNKNOWN_COMMAND: &str = "zzzzzzzzzzzzzzzzzzzzzzzz";
+
+// Hash of the memory without the ignored positions, which are sorted
+fn memory_hash(vm: &VM, ignored: &[usize]) -> u64 {
+    let memory = vm.dbg_get_memory();
+    let mut hasher = DefaultHasher::new();
+    let mut start = 0;
+
+    for &position in ignored {
+        memory[start .. position].hash(&mut hasher);
+        start = position + 1;
+    }
+
+    memory[start ..].hash(&mut hasher);
+    hasher.finish()
+}
+
+// Positions holding the last command typed, which differ between two commands leaving the game as it was
+fn input_buffer(vm: &VM) -> Vec<usize> {
+    let (looking, _) = play(vm, "look");
+    let (unknown, _) = play(vm, UNKNOWN_COMMAND);
+
+    (0 .. looking.dbg_get_memory().len())
+        .filter(|&position| looking.dbg_get_memory()[position] != unknown.dbg_get_memory()[position])
+        .collect()
+}
+
+// Lines listed with a dash after the first line starting with the header, up to the next empty line
+fn listed(text: &str, header: &str) -> Vec<String> {
+    text.lines()
+        .skip_while(|line| !line.starts_with(header))
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.strip_prefix("- "))
+        .map(String::from)
+        .collect()
+}
+
+fn play(vm: &VM, command: &str) -> (VM, String) {
+    let mut vm = vm.clone();
+
+    vm.dbg_take_captured_output();
+    vm.input_command(&format!("{}\n", command));
+    vm.run();
+
+    let output = vm.dbg_take_captured_output();
+
+    (vm, output)
+}
+
+impl Explorer {
+    pub fn new(current_location: u16) -> Self {
+        Self { current_location, budget: 1000 }
+    }
+
+    pub fn budget(mut self, budget: usize) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    // Commands worth trying on the state of the VM, along with the description of the room
+    pub fn commands(vm: &VM) -> (String, Vec<String>) {
+        let (_, room) = play(vm, "look");
+        let (_, inventory) = play(vm, "inv");
+        let exits = listed(&room, "There ");
+        let takes = listed(&room, "Things of interest here:").into_iter().map(|item| format!("take {}", item));
+        let uses = listed(&inventory, "Your inventory:").into_iter().map(|item| format!("use {}", item));
+
+        (room, exits.into_iter().chain(takes).chain(uses).collect())
+    }
+
+    // Explores from a VM waiting for a command, which gets its output disabled and captured
+    pub fn explore(&self, mut vm: VM) -> Exploration {
+        vm.dbg_set_output_enabled(false);
+        vm.dbg_set_output_capture(true);
+
+        let mut exploration = Exploration { states: vec![], transitions: vec![], codes: vec![], complete: true };
+        let mut ids: HashMap<u64, usize> = HashMap::new();
+        let mut pending = VecDeque::new();
+        let ignored = input_buffer(&vm);
+
+        self.add_state(&mut exploration, &mut ids, &mut pending, vm, String::new(), &ignored);
+
+        while let Some((id, vm)) = pending.pop_front() {
+            let (room, commands) = Self::commands(&vm);
+
+            exploration.states[id].room = room;
+
+            for command in commands {
+                let (next, output) = play(&vm, &command);
+                let to = match ids.get(&memory_hash(&next, &ignored)) {
+                    Some(&to) => Some(to),
+                    None      => self.add_state(&mut exploration, &mut ids, &mut pending, next, output.clone(), &ignored)
+                };
+
+                for code in extract_codes(&output) {
+                    if !exploration.codes.iter().any(|(_, found)| *found == code.value) {
+                        exploration.codes.push((id, code.value));
+                    }
+                }
+
+                exploration.transitions.push(Transition { from: id, command, to });
+            }
+        }
+
+        exploration
+    }
+
+    // The room of a state is the output that led to it until it gets explored, which is kept if the game halted
+    fn add_state(
+        &self,
+        exploration: &mut Exploration,
+        ids: &mut HashMap<u64, usize>,
+        pending: &mut VecDeque<(usize, VM)>,
+        vm: VM,
+        room: String,
+        ignored: &[usize]
+    ) -> Option<usize> {
+        if exploration.states.len() >= self.budget {
+            exploration.complete = false;
+            return None;
+        }
+
+        let id = exploration.states.len();
+        let halted = vm.is_halted();
+
+        ids.insert(memory_hash(&vm, ignored), id);
+        exploration.states.push(State {
+            id,
+            location: vm.dbg_get_memory()[self.current_location as usize] as usize,
+            room,
+            halted
+        });
+
+        if !halted {
+            pending.push_back((id, vm));
+        }
+
+        Some(id)
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt::Write;
+use crate::world::{Location, World};
+
+/*
+Writers of the game world for other tools:
+
+    DOT:      Graphviz graph with a node per location (listing its items and enter fn) and an edge per exit,
+              highlighting the given locations and the ones with items
+    Mermaid:  flowchart with the same nodes, edges and colours as the DOT graph
+    GraphML:  graph with the same nodes, edges and colours as the DOT graph, as label and fill data of each node and
+              label and synthetic data of each edge
+    JSON:     every location and item with all their fields, using the following schema
+
+        {
+            "locations": [
+                {
+                    "id": number, "name": string, "description": string, "enter_fn": number | null,
+                    "exits": [{ "label": string, "target": number, "synthetic": boolean }]
+                }
+            ],
+            "items": [
+                { "id": number, "name": string, "description": string, "location": number, "use_fn": number | null }
+            ]
+        }
+
+Synthetic exits are the moves done through code instead of through the exits of the location.
+*/
+
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"'          => json.push_str("\\\""),
+            '\\'         => json.push_str("\\\\"),
+            '\n'         => json.push_str("\\n"),
+            '\t'         => json.push_str("\\t"),
+            c if c < ' ' => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c            => json.push(c)
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+fn json_address(address: u16) -> String {
+    if address == 0 { "null".to_string() } else { address.to_string() }
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn node_label(world: &World, location: &Location) -> Vec<String> {
+    let items: Vec<&str> = world.items_at(location.id).map(|item| item.name.as_str()).collect();
+
+    [
+        format!("[{}] {}", location.id, location.name),
+        items.join(", "),
+        if location.enter_fn == 0 { String::new() } else { format!("enter_fn {}", location.enter_fn) }
+    ]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn node_fill(world: &World, location: &Location, highlighted: &[usize]) -> Option<&'static str> {
+    match location.id {
+        id if highlighted.contains(&id)           => Some("palegreen"),
+        id if world.items_at(id).next().is_some() => Some("paleturquoise"),
+        _                                         => None
+    }
+}
+
+pub fn to_dot(world: &World, highlighted: &[usize]) -> String {
+    let mut dot = String::from("digraph G {\n");
+
+    for location in &world.locations {
+        let label = node_label(world, location).join("\n");
+        let fill = node_fill(world, location, highlighted)
+            .map(|fill| format!(r#", fillcolor="{}", style="filled""#, fill))
+            .unwrap_or_default();
+
+        writeln!(dot, r#"    {id} [label="{label}"{fill}]"#, id = location.id, label = label, fill = fill).unwrap();
+    }
+
+    for location in &world.locations {
+        for exit in &location.connections {
+            writeln!(dot, r#"    {} -> {} [label="{}"]"#, location.id, exit.target, exit.label).unwrap();
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+pub fn to_mermaid(world: &World, highlighted: &[usize]) -> String {
+    let mut mermaid = String::from("flowchart TD\n");
+
+    for location in &world.locations {
+        let label: Vec<String> = node_label(world, location).iter().map(|line| line.replace('"', "#quot;")).collect();
+
+        writeln!(mermaid, r#"    L{}["{}"]"#, location.id, label.join("<br/>")).unwrap();
+    }
+
+    for location in &world.locations {
+        for exit in &location.connections {
+            writeln!(mermaid, r#"    L{} -->|"{}"| L{}"#, location.id, exit.label.replace('"', "#quot;"), exit.target).unwrap();
+        }
+    }
+
+    for fill in ["palegreen", "paleturquoise"] {
+        let filled: Vec<String> = world.locations
+            .iter()
+            .filter(|location| node_fill(world, location, highlighted) == Some(fill))
+            .map(|location| format!("L{}", location.id))
+            .collect();
+
+        if !filled.is_empty() {
+            writeln!(mermaid, "    classDef {fill} fill:{fill}", fill = fill).unwrap();
+            writeln!(mermaid, "    class {} {}", filled.join(","), fill).unwrap();
+        }
+    }
+
+    mermaid
+}
+
+pub fn to_graphml(world: &World, highlighted: &[usize]) -> String {
+    let mut graphml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+        "  <key id=\"fill\" for=\"node\" attr.name=\"fill\" attr.type=\"string\"/>\n",
+        "  <key id=\"exit\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n",
+        "  <key id=\"synthetic\" for=\"edge\" attr.name=\"synthetic\" attr.type=\"boolean\"/>\n",
+        "  <graph id=\"G\" edgedefault=\"directed\">\n"
+    ));
+
+    for location in &world.locations {
+        writeln!(graphml, r#"    <node id="{}">"#, location.id).unwrap();
+        writeln!(graphml, r#"      <data key="label">{}</data>"#, xml_escape(&node_label(world, location).join("\n"))).unwrap();
+
+        if let Some(fill) = node_fill(world, location, highlighted) {
+            writeln!(graphml, r#"      <data key="fill">{}</data>"#, fill).unwrap();
+        }
+
+        graphml.push_str("    </node>\n");
+    }
+
+    for location in &world.locations {
+        for exit in &location.connections {
+            writeln!(graphml, r#"    <edge source="{}" target="{}">"#, location.id, exit.target).unwrap();
+            writeln!(graphml, r#"      <data key="exit">{}</data>"#, xml_escape(&exit.label)).unwrap();
+            writeln!(graphml, r#"      <data key="synthetic">{}</data>"#, exit.synthetic).unwrap();
+            graphml.push_str("    </edge>\n");
+        }
+    }
+
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+}
+
+pub fn to_json(world: &World) -> String {
+    let mut json = String::from("{\n    \"locations\": [");
+
+    for (idx, location) in world.locations.iter().enumerate() {
+        let exits: Vec<String> = location.connections
+            .iter()
+            .map(|exit| format!(
+                r#"{{ "label": {}, "target": {}, "synthetic": {} }}"#,
+                json_string(&exit.label), exit.target, exit.synthetic
+            ))
+            .collect();
+
+        json.push_str(if idx == 0 { "\n" } else { ",\n" });
+        writeln!(json, "        {{").unwrap();
+        writeln!(json, r#"            "id": {},"#, location.id).unwrap();
+        writeln!(json, r#"            "name": {},"#, json_string(&location.name)).unwrap();
+        writeln!(json, r#"            "description": {},"#, json_string(&location.description)).unwrap();
+        writeln!(json, r#"            "enter_fn": {},"#, json_address(location.enter_fn)).unwrap();
+
+        if exits.is_empty() {
+            writeln!(json, r#"            "exits": []"#).unwrap();
+        } else {
+            writeln!(json, "            \"exits\": [\n                {}\n            ]", exits.join(",\n                ")).unwrap();
+        }
+
+        json.push_str("        }");
+    }
+
+    json.push_str("\n    ],\n    \"items\": [");
+
+    for (idx, item) in world.items.iter().enumerate() {
+        json.push_str(if idx == 0 { "\n" } else { ",\n" });
+        write!(
+            json,
+            r#"        {{ "id": {}, "name": {}, "description": {}, "location": {}, "use_fn": {} }}"#,
+            item.id, json_string(&item.name), json_string(&item.description), item.location, json_address(item.use_fn)
+        ).unwrap();
+    }
+
+    json.push_str("\n    ]\n}\n");
+    json
+}
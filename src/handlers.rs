@@ -0,0 +1,283 @@
+use std::{collections::BTreeSet, fmt::{self, Display, Formatter}};
+use crate::{
+    dataflow::{register_defs, AbstractValue, Program, Values},
+    ir::{join, literal, BinOp, Expr, Function, Op, Stmt},
+    ssa::immediate_dominators,
+    Number, VM
+};
+
+/*
+Enter fns of locations and use fns of items are analyzed on the lifted program, with constant propagation resolving the
+addresses written through registers:
+
+    Reads:   memory addresses loaded with a constant address
+    Writes:  memory addresses stored with a constant address, along with the value if it's constant too
+    Calls:   calls with the constant values of the inputs of the callee, such as f(r0=2702)
+    Moves:   writes of a constant location to the current location pointer, along with the conditions of the branches
+             leading to them
+    Prints:  output of the calls, found by running them with the constant registers on a clone of the VM, since the
+             strings printed by handlers are only decrypted while printing them
+
+A block is guarded by the branch of its immediate dominator if it can only be reached through one of the edges of the
+branch and the dominator isn't a loop header (whose branch is the exit condition of the loop), and the conditions of a
+move are the guards of its block and of its dominators. Conditions are described with
+the expressions defining the tested register within the block, such as 6049(r0=4, r1=1) == 6, and the constant inputs
+of the calls they test directly are kept as the arguments of the move (such as 2702 for 6012(r0=2702) == 0).
+*/
+
+const EMULATION_STEPS: usize = 1_000_000;
+
+pub struct Move {
+    pub address: u16,
+    pub target: usize,
+    pub conditions: Vec<String>,
+    pub arguments: Vec<u16>
+}
+
+pub struct Handler {
+    pub address: u16,
+    pub reads: BTreeSet<u16>,
+    pub writes: Vec<(u16, Option<u16>)>,
+    pub calls: Vec<(u16, String)>,
+    pub moves: Vec<Move>,
+    pub prints: Vec<String>
+}
+
+fn constant_inputs(values: &Values, program: &Program, site: u16, target: &Number) -> Vec<(usize, u16)> {
+    program.summaries
+        .call(site, target)
+        .inputs
+        .into_iter()
+        .filter_map(|r| match values.before.get(&site).map(|state| state.registers[r]) {
+            Some(AbstractValue::Const(value)) => Some((r, value)),
+            _                                 => None
+        })
+        .collect()
+}
+
+struct Context<'a> {
+    function: &'a Function,
+    program: &'a Program,
+    values: Values
+}
+
+impl Context<'_> {
+    fn value(&self, address: u16, number: &Number) -> AbstractValue {
+        self.values.before.get(&address).map_or(AbstractValue::Unknown, |state| state.value(number))
+    }
+
+    fn call(&self, block: u16, index: usize, target: &Number) -> String {
+        let stmt = &self.function.blocks[&block].stmts[index];
+        let arguments: Vec<String> = constant_inputs(&self.values, self.program, stmt.address, target)
+            .iter()
+            .map(|(r, value)| format!("r{}={}", r, value))
+            .collect();
+
+        format!("{}({})", self.describe(block, index, target), arguments.join(", "))
+    }
+
+    // Expression of a number at the given statement of a block, following the definitions within the block
+    fn describe(&self, block: u16, index: usize, number: &Number) -> String {
+        let stmts = &self.function.blocks[&block].stmts;
+        let Number::Register(r) = *number else { return literal(number).unwrap().to_string() };
+        let definition = (0 .. index)
+            .rev()
+            .find(|&idx| register_defs(&stmts[idx], &self.program.summaries).contains(&r));
+
+        match definition.map(|idx| (idx, &stmts[idx].op)) {
+            Some((idx, Op::Assign(_, Expr::Binary(op, b, c)))) => {
+                format!("{} {} {}", self.describe(block, idx, b), op.symbol(), self.describe(block, idx, c))
+            }
+            Some((idx, Op::Assign(_, Expr::Load(b))))  => format!("m[{}]", self.describe(block, idx, b)),
+            Some((idx, Op::Assign(_, Expr::Not(b))))   => format!("not {}", self.describe(block, idx, b)),
+            Some((idx, Op::Assign(_, Expr::Value(b)))) => self.describe(block, idx, b),
+            Some((idx, Op::Call(target)))              => self.call(block, idx, target),
+            _ => match self.value(stmts[index].address, number) {
+                AbstractValue::Const(x) => x.to_string(),
+                AbstractValue::Entry(x) => format!("r{}", x),
+                AbstractValue::Unknown  => format!("r{}", r)
+            }
+        }
+    }
+
+    // Condition holding when going from a block ending with a branch to the given successor
+    fn condition(&self, block: u16, successor: u16) -> Option<String> {
+        let stmts = &self.function.blocks[&block].stmts;
+        let last = stmts.len() - 1;
+        let Op::Branch(number, jump_if, target) = &stmts[last].op else { return None };
+        let jumping = literal(target) == Some(successor) && stmts[last].next() != successor;
+        let nonzero = jumping == *jump_if;
+        let definition = match number {
+            Number::Register(r) => (0 .. last).rev().find(|&idx| register_defs(&stmts[idx], &self.program.summaries).contains(r)),
+            Number::Literal(_)  => None
+        };
+
+        Some(match definition.map(|idx| (idx, &stmts[idx].op)) {
+            Some((idx, Op::Assign(_, Expr::Binary(op @ (BinOp::Eq | BinOp::Gt), b, c)))) => {
+                let symbol = match (op, nonzero) {
+                    (BinOp::Eq, true)  => "==",
+                    (BinOp::Eq, false) => "!=",
+                    (_, true)          => ">",
+                    (_, false)         => "<="
+                };
+
+                format!("{} {} {}", self.describe(block, idx, b), symbol, self.describe(block, idx, c))
+            }
+            _ => format!("{} {} 0", self.describe(block, last, number), if nonzero { "!=" } else { "==" })
+        })
+    }
+
+    // Constant inputs of the call whose result the branch ending the block tests
+    fn tested_arguments(&self, block: u16) -> Vec<u16> {
+        let stmts = &self.function.blocks[&block].stmts;
+        let last = stmts.len() - 1;
+        let Op::Branch(Number::Register(r), ..) = &stmts[last].op else { return vec![] };
+        let definition = (0 .. last).rev().find(|&idx| register_defs(&stmts[idx], &self.program.summaries).contains(r));
+
+        match definition.map(|idx| &stmts[idx]) {
+            Some(stmt @ Stmt { op: Op::Call(target), .. }) => {
+                constant_inputs(&self.values, self.program, stmt.address, target).into_iter().map(|(_, value)| value).collect()
+            }
+            _ => vec![]
+        }
+    }
+
+    // Branches (dominator and successor) which the block can only be reached through
+    fn guards(&self, block: u16) -> Vec<(u16, u16)> {
+        let idom = immediate_dominators(self.function);
+        let predecessors = self.function.predecessors();
+        let dominates = |dominator: u16, mut block: u16| loop {
+            match idom.get(&block) {
+                _ if block == dominator      => return true,
+                Some(&next) if next != block => block = next,
+                _                            => return false
+            }
+        };
+        let mut guards = vec![];
+        let mut current = block;
+
+        while let Some(&dominator) = idom.get(&current).filter(|&&dominator| dominator != current) {
+            let loop_header = predecessors[&dominator].iter().any(|&predecessor| dominates(dominator, predecessor));
+
+            if predecessors[&current] == [dominator] && !loop_header {
+                guards.push((dominator, current));
+            }
+
+            current = dominator;
+        }
+
+        guards.reverse();
+        guards
+    }
+}
+
+impl Move {
+    // Label of the exit taking the player to the target, after the given command if any
+    pub fn label(&self, command: Option<&str>) -> String {
+        let conditions = (!self.conditions.is_empty()).then(|| format!("if {}", self.conditions.join(" and ")));
+
+        match [command.map(String::from), conditions].into_iter().flatten().collect::<Vec<String>>()[..] {
+            []        => "always".to_string(),
+            ref parts => parts.join(" ")
+        }
+    }
+}
+
+impl Handler {
+    // The program should have been lifted with the handler as one of its roots
+    pub fn analyze(program: &Program, address: u16, current_location: u16) -> Self {
+        let function = &program.functions[&address];
+        let context = Context { function, program, values: Values::compute(function, &program.summaries) };
+        let mut handler = Self { address, reads: BTreeSet::new(), writes: vec![], calls: vec![], moves: vec![], prints: vec![] };
+
+        for (&start, block) in &function.blocks {
+            for (idx, stmt) in block.stmts.iter().enumerate() {
+                match &stmt.op {
+                    Op::Assign(_, Expr::Load(b)) => {
+                        if let AbstractValue::Const(address) = context.value(stmt.address, b) {
+                            handler.reads.insert(address);
+                        }
+                    }
+                    Op::Store(a, b) => {
+                        let AbstractValue::Const(target) = context.value(stmt.address, a) else { continue };
+                        let value = match context.value(stmt.address, b) {
+                            AbstractValue::Const(value) => Some(value),
+                            _                           => None
+                        };
+
+                        handler.writes.push((target, value));
+
+                        if let (true, Some(location)) = (target == current_location, value) {
+                            let guards = context.guards(start);
+
+                            handler.moves.push(Move {
+                                address: stmt.address,
+                                target: location as usize,
+                                conditions: guards.iter().filter_map(|&(dominator, successor)| context.condition(dominator, successor)).collect(),
+                                arguments: guards.iter().flat_map(|&(dominator, _)| context.tested_arguments(dominator)).collect()
+                            });
+                        }
+                    }
+                    Op::Call(target) => handler.calls.push((stmt.address, context.call(start, idx, target))),
+                    _ => ()
+                }
+            }
+        }
+
+        handler
+    }
+
+    // Runs each call with a constant target on a clone of the VM, keeping what gets printed if it returns
+    pub fn emulate_prints(&mut self, vm: &VM, program: &Program) {
+        let function = &program.functions[&self.address];
+        let values = Values::compute(function, &program.summaries);
+
+        for (site, _) in function.call_sites().into_iter().filter(|(_, target)| literal(target).is_some()) {
+            let mut emulated = vm.clone();
+
+            for (register, value) in values.before[&site].registers.iter().enumerate() {
+                if let AbstractValue::Const(value) = value {
+                    emulated.dbg_set_register(register, *value);
+                }
+            }
+
+            emulated.dbg_set_output_enabled(false);
+            emulated.dbg_set_output_capture(true);
+            emulated.dbg_set_pc(site);
+            emulated.dbg_add_breakpoint(site + 2);
+            emulated.dbg_set_step_limit(Some(EMULATION_STEPS));
+            emulated.run();
+
+            let output = emulated.dbg_take_captured_output();
+
+            if emulated.dbg_get_pc() == site + 2 && !output.is_empty() {
+                self.prints.push(output);
+            }
+        }
+    }
+}
+
+impl Display for Handler {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let writes = self.writes
+            .iter()
+            .map(|(address, value)| format!("m[{}] = {}", address, value.map_or("?".to_string(), |value| value.to_string())));
+
+        writeln!(f, "handler {}", self.address)?;
+        writeln!(f, "    reads:   {}", join(self.reads.iter()))?;
+        writeln!(f, "    writes:  {}", writes.collect::<Vec<String>>().join(", "))?;
+        writeln!(f, "    calls:   {}", self.calls.iter().map(|(_, call)| call.as_str()).collect::<Vec<&str>>().join(", "))?;
+
+        for print in &self.prints {
+            writeln!(f, "    prints:  {:?}", print)?;
+        }
+
+        for movement in &self.moves {
+            let conditions = if movement.conditions.is_empty() { "always".to_string() } else { movement.conditions.join(" and ") };
+
+            writeln!(f, "    moves:   to {} ({}) at {}", movement.target, conditions, movement.address)?;
+        }
+
+        Ok(())
+    }
+}
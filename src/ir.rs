@@ -0,0 +1,376 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt::{self, Display, Formatter}};
+use crate::{Instruction, Literal, Number, Register};
+
+/*
+The IR makes the effects of every instruction explicit: each operation either assigns an expression to a register
+or performs a side effect on memory, the stack, the input/output or the control flow.
+
+Instructions are lifted one by one and grouped into basic blocks, which in turn are grouped into functions by following
+the control flow from an entry point. Calls don't split blocks: their effects are described by the function summaries
+computed in the dataflow module.
+*/
+
+const MAX_SIZE: u32 = 32768;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    And,
+    Eq,
+    Gt,
+    Mod,
+    Mul,
+    Or
+}
+
+impl BinOp {
+    pub fn eval(&self, a: u16, b: u16) -> Option<u16> {
+        let (a, b) = (a as u32, b as u32);
+
+        match self {
+            BinOp::Add => Some((a + b) % MAX_SIZE),
+            BinOp::And => Some(a & b),
+            BinOp::Eq  => Some((a == b).into()),
+            BinOp::Gt  => Some((a > b).into()),
+            BinOp::Mod => a.checked_rem(b),
+            BinOp::Mul => Some((a * b) % MAX_SIZE),
+            BinOp::Or  => Some(a | b)
+        }.map(|x| x as u16)
+    }
+
+    pub(crate) fn symbol(&self) -> &str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::And => "&",
+            BinOp::Eq  => "==",
+            BinOp::Gt  => ">",
+            BinOp::Mod => "%",
+            BinOp::Mul => "*",
+            BinOp::Or  => "|"
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Binary(BinOp, Number, Number),
+    Input,
+    Load(Number),
+    Not(Number),
+    Pop,
+    Value(Number)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Assign(Register, Expr),
+    Branch(Number, bool, Number),
+    Call(Number),
+    Halt,
+    Invalid(Literal),
+    Jump(Number),
+    Nop,
+    Output(Number),
+    Push(Number),
+    Return,
+    Store(Number, Number)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Access {
+    Input,
+    Memory(Option<u16>),
+    Output,
+    Register(Register),
+    Stack
+}
+
+impl From<&Instruction> for Op {
+    fn from(instruction: &Instruction) -> Self {
+        match *instruction {
+            Instruction::Add(a, b, c)                => Op::Assign(a, Expr::Binary(BinOp::Add, b, c)),
+            Instruction::BitwiseAnd(a, b, c)         => Op::Assign(a, Expr::Binary(BinOp::And, b, c)),
+            Instruction::BitwiseNot(a, b)            => Op::Assign(a, Expr::Not(b)),
+            Instruction::BitwiseOr(a, b, c)          => Op::Assign(a, Expr::Binary(BinOp::Or, b, c)),
+            Instruction::CompareEquals(a, b, c)      => Op::Assign(a, Expr::Binary(BinOp::Eq, b, c)),
+            Instruction::CompareGreaterThan(a, b, c) => Op::Assign(a, Expr::Binary(BinOp::Gt, b, c)),
+            Instruction::FunctionCall(a)             => Op::Call(a),
+            Instruction::FunctionReturn              => Op::Return,
+            Instruction::Halt                        => Op::Halt,
+            Instruction::Jump(a)                     => Op::Jump(a),
+            Instruction::JumpIfFalse(a, b)           => Op::Branch(a, false, b),
+            Instruction::JumpIfTrue(a, b)            => Op::Branch(a, true, b),
+            Instruction::MemoryRead(a, b)            => Op::Assign(a, Expr::Load(b)),
+            Instruction::MemoryWrite(a, b)           => Op::Store(a, b),
+            Instruction::Mod(a, b, c)                => Op::Assign(a, Expr::Binary(BinOp::Mod, b, c)),
+            Instruction::Multiply(a, b, c)           => Op::Assign(a, Expr::Binary(BinOp::Mul, b, c)),
+            Instruction::NoOp                        => Op::Nop,
+            Instruction::Pop(a)                      => Op::Assign(a, Expr::Pop),
+            Instruction::PrintChar(a)                => Op::Output(a),
+            Instruction::Push(a)                     => Op::Push(a),
+            Instruction::ReadChar(a)                 => Op::Assign(a, Expr::Input),
+            Instruction::SetRegister(a, b)           => Op::Assign(a, Expr::Value(b)),
+            Instruction::Unknown(opcode)             => Op::Invalid(opcode)
+        }
+    }
+}
+
+impl Expr {
+    pub fn operands(&self) -> Vec<Number> {
+        match *self {
+            Expr::Binary(_, a, b)             => vec![a, b],
+            Expr::Load(a) | Expr::Not(a)
+                | Expr::Value(a)              => vec![a],
+            Expr::Input | Expr::Pop           => vec![]
+        }
+    }
+}
+
+impl Op {
+    pub fn operands(&self) -> Vec<Number> {
+        match self {
+            Op::Assign(_, expr)                        => expr.operands(),
+            Op::Branch(a, _, b) | Op::Store(a, b)      => vec![*a, *b],
+            Op::Call(a) | Op::Jump(a) | Op::Output(a)
+                | Op::Push(a)                          => vec![*a],
+            Op::Halt | Op::Invalid(_) | Op::Nop
+                | Op::Return                           => vec![]
+        }
+    }
+
+    pub fn register_uses(&self) -> Vec<Register> {
+        self.operands()
+            .into_iter()
+            .filter_map(|number| match number {
+                Number::Register(x) => Some(x),
+                Number::Literal(_)  => None
+            })
+            .collect()
+    }
+
+    pub fn register_def(&self) -> Option<Register> {
+        match self {
+            Op::Assign(a, _) => Some(*a),
+            _                => None
+        }
+    }
+
+    pub fn reads(&self) -> Vec<Access> {
+        let mut reads: Vec<Access> = self.register_uses().into_iter().map(Access::Register).collect();
+
+        match self {
+            Op::Assign(_, Expr::Load(a)) => reads.push(Access::Memory(literal(a))),
+            Op::Assign(_, Expr::Pop)     => reads.push(Access::Stack),
+            Op::Assign(_, Expr::Input)   => reads.push(Access::Input),
+            Op::Return                   => reads.push(Access::Stack),
+            _                            => ()
+        }
+
+        reads
+    }
+
+    pub fn writes(&self) -> Vec<Access> {
+        match self {
+            Op::Assign(a, Expr::Pop) => vec![Access::Register(*a), Access::Stack],
+            Op::Assign(a, _)         => vec![Access::Register(*a)],
+            Op::Call(_) | Op::Push(_)
+                | Op::Return         => vec![Access::Stack],
+            Op::Output(_)            => vec![Access::Output],
+            Op::Store(a, _)          => vec![Access::Memory(literal(a))],
+            _                        => vec![]
+        }
+    }
+
+    pub fn is_terminator(&self) -> bool {
+        matches!(self, Op::Branch(..) | Op::Halt | Op::Invalid(_) | Op::Jump(_) | Op::Return)
+    }
+
+    pub fn successors(&self, next: u16) -> Vec<u16> {
+        match self {
+            Op::Branch(_, _, a) => literal(a).into_iter().chain([next]).collect(),
+            Op::Jump(a)         => literal(a).into_iter().collect(),
+            Op::Halt | Op::Invalid(_)
+                | Op::Return    => vec![],
+            _                   => vec![next]
+        }
+    }
+
+    pub fn fmt_with(&self, f: &mut Formatter, name: &dyn Fn(Register, bool) -> String) -> fmt::Result {
+        let n = |number: &Number| match number {
+            Number::Literal(x)  => x.to_string(),
+            Number::Register(x) => name(*x, false)
+        };
+
+        match self {
+            Op::Assign(a, expr) => {
+                write!(f, "{} = ", name(*a, true))?;
+
+                match expr {
+                    Expr::Binary(op, b, c) => write!(f, "{} {} {}", n(b), op.symbol(), n(c)),
+                    Expr::Input            => write!(f, "input"),
+                    Expr::Load(b)          => write!(f, "m[{}]", n(b)),
+                    Expr::Not(b)           => write!(f, "not {}", n(b)),
+                    Expr::Pop              => write!(f, "pop"),
+                    Expr::Value(b)         => write!(f, "{}", n(b))
+                }
+            }
+            Op::Branch(a, true, b)  => write!(f, "jmp {} if {}", n(b), n(a)),
+            Op::Branch(a, false, b) => write!(f, "jmp {} if not {}", n(b), n(a)),
+            Op::Call(a)             => write!(f, "call {}", n(a)),
+            Op::Halt                => write!(f, "halt"),
+            Op::Invalid(opcode)     => write!(f, "invalid {}", opcode),
+            Op::Jump(a)             => write!(f, "jmp {}", n(a)),
+            Op::Nop                 => write!(f, "nop"),
+            Op::Output(a)           => write!(f, "output {}", n(a)),
+            Op::Push(a)             => write!(f, "push {}", n(a)),
+            Op::Return              => write!(f, "ret"),
+            Op::Store(a, b)         => write!(f, "m[{}] = {}", n(a), n(b))
+        }
+    }
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.fmt_with(f, &|register, _| format!("r{}", register))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stmt {
+    pub address: u16,
+    pub size: u16,
+    pub op: Op
+}
+
+impl Stmt {
+    pub fn lift(memory: &[u16], address: u16) -> Self {
+        let instruction = Instruction::decode(memory, address);
+
+        Self { address, size: instruction.size(), op: Op::from(&instruction) }
+    }
+
+    pub fn next(&self) -> u16 {
+        self.address + self.size
+    }
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.address, self.op)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub start: u16,
+    pub stmts: Vec<Stmt>,
+    pub successors: Vec<u16>
+}
+
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub entry: u16,
+    pub blocks: BTreeMap<u16, Block>
+}
+
+impl Function {
+    pub fn lift(memory: &[u16], entry: u16) -> Self {
+        let mut stmts = BTreeMap::new();
+        let mut leaders = BTreeSet::from([entry]);
+        let mut pending = vec![entry];
+
+        while let Some(address) = pending.pop() {
+            if address as usize >= memory.len() || stmts.contains_key(&address) {
+                continue;
+            }
+
+            let stmt = Stmt::lift(memory, address);
+            let successors = stmt.op.successors(stmt.next());
+
+            if stmt.op.is_terminator() {
+                leaders.extend(&successors);
+            }
+
+            pending.extend(successors);
+            stmts.insert(address, stmt);
+        }
+
+        let blocks = leaders
+            .iter()
+            .filter(|leader| stmts.contains_key(leader))
+            .map(|&start| {
+                let mut block = Block { start, stmts: vec![], successors: vec![] };
+                let mut address = start;
+
+                while let Some(stmt) = stmts.get(&address) {
+                    block.stmts.push(*stmt);
+                    address = stmt.next();
+
+                    if stmt.op.is_terminator() || leaders.contains(&address) {
+                        block.successors = stmt.op.successors(address);
+                        break;
+                    }
+                }
+
+                block.successors.retain(|successor| stmts.contains_key(successor));
+                (start, block)
+            })
+            .collect();
+
+        Self { entry, blocks }
+    }
+
+    pub fn predecessors(&self) -> BTreeMap<u16, Vec<u16>> {
+        let mut predecessors: BTreeMap<u16, Vec<u16>> = self.blocks.keys().map(|&start| (start, vec![])).collect();
+
+        for block in self.blocks.values() {
+            for successor in &block.successors {
+                predecessors.entry(*successor).or_default().push(block.start);
+            }
+        }
+
+        predecessors
+    }
+
+    pub fn stmts(&self) -> impl Iterator<Item = &Stmt> {
+        self.blocks.values().flat_map(|block| &block.stmts)
+    }
+
+    pub fn call_sites(&self) -> Vec<(u16, Number)> {
+        self.stmts()
+            .filter_map(|stmt| match stmt.op {
+                Op::Call(target) => Some((stmt.address, target)),
+                _                => None
+            })
+            .collect()
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "function {}", self.entry)?;
+
+        for block in self.blocks.values() {
+            writeln!(f, "  block {}", block.start)?;
+
+            for stmt in &block.stmts {
+                writeln!(f, "    {}", stmt)?;
+            }
+
+            writeln!(f, "    -> {}", join(&block.successors))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn literal(number: &Number) -> Option<u16> {
+    match number {
+        Number::Literal(x)  => Some(*x),
+        Number::Register(_) => None
+    }
+}
+
+pub(crate) fn join<T: Display>(values: impl IntoIterator<Item = T>) -> String {
+    values.into_iter().map(|value| value.to_string()).collect::<Vec<String>>().join(", ")
+}
@@ -1,10 +1,36 @@
-use std::{collections::{HashSet, VecDeque}, fs};
+pub mod asm;
+pub mod callgraph;
+pub mod cli;
+pub mod codes;
+pub mod coins;
+pub mod completion;
+pub mod dataflow;
+pub mod debugger;
+pub mod discovery;
+pub mod editor;
+pub mod explorer;
+pub mod export;
+pub mod handlers;
+pub mod ir;
+pub mod patch;
+pub mod replay;
+pub mod route;
+pub mod ssa;
+pub mod teleporter;
+pub mod terminal;
+pub mod tui;
+pub mod vault;
+pub mod world;
+
+use std::{collections::{BTreeSet, HashSet, VecDeque}, fs};
+use patch::Patch;
 
 const MAX_SIZE: usize = 32768;
 
 pub type Literal = u16;
 pub type Register = usize;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Number {
     Literal(Literal),
     Register(Register)
@@ -12,14 +38,19 @@ pub enum Number {
 
 impl Number {
     pub fn from(value: u16) -> Self {
+        Self::parse(value).unwrap_or_else(|| panic!("Invalid number {}", value))
+    }
+
+    pub fn parse(value: u16) -> Option<Self> {
         match value {
-            0 ..= 32767     => Number::Literal(value),
-            32768 ..= 32775 => Number::Register(value as usize - 32768),
-            _               => panic!("Invalid number {}", value)
+            0 ..= 32767     => Some(Number::Literal(value)),
+            32768 ..= 32775 => Some(Number::Register(value as usize - 32768)),
+            _               => None
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Instruction {
     Add(Register, Number, Number),
     BitwiseAnd(Register, Number, Number),
@@ -46,15 +77,80 @@ pub enum Instruction {
     Unknown(Literal)
 }
 
+impl Instruction {
+    pub fn decode(memory: &[u16], address: u16) -> Self {
+        let opcode = memory.get(address as usize).copied().unwrap_or(0);
+        let operands = memory.get(address as usize + 1 ..).unwrap_or(&[]);
+
+        Self::decode_operands(opcode, operands).unwrap_or(Instruction::Unknown(opcode))
+    }
+
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::Halt | Instruction::FunctionReturn | Instruction::NoOp | Instruction::Unknown(_) => 1,
+            Instruction::FunctionCall(_) | Instruction::Jump(_) | Instruction::Pop(_) | Instruction::PrintChar(_)
+                | Instruction::Push(_) | Instruction::ReadChar(_) => 2,
+            Instruction::BitwiseNot(..) | Instruction::JumpIfFalse(..) | Instruction::JumpIfTrue(..)
+                | Instruction::MemoryRead(..) | Instruction::MemoryWrite(..) | Instruction::SetRegister(..) => 3,
+            _ => 4
+        }
+    }
+
+    fn decode_operands(opcode: u16, operands: &[u16]) -> Option<Self> {
+        let n = |idx: usize| operands.get(idx).copied().and_then(Number::parse);
+        let r = |idx: usize| match n(idx)? {
+            Number::Register(x) => Some(x),
+            Number::Literal(_)  => None
+        };
+
+        Some(match opcode {
+            0  => Instruction::Halt,
+            1  => Instruction::SetRegister       (r(0)?, n(1)?),
+            2  => Instruction::Push              (n(0)?),
+            3  => Instruction::Pop               (r(0)?),
+            4  => Instruction::CompareEquals     (r(0)?, n(1)?, n(2)?),
+            5  => Instruction::CompareGreaterThan(r(0)?, n(1)?, n(2)?),
+            6  => Instruction::Jump              (n(0)?),
+            7  => Instruction::JumpIfTrue        (n(0)?, n(1)?),
+            8  => Instruction::JumpIfFalse       (n(0)?, n(1)?),
+            9  => Instruction::Add               (r(0)?, n(1)?, n(2)?),
+            10 => Instruction::Multiply          (r(0)?, n(1)?, n(2)?),
+            11 => Instruction::Mod               (r(0)?, n(1)?, n(2)?),
+            12 => Instruction::BitwiseAnd        (r(0)?, n(1)?, n(2)?),
+            13 => Instruction::BitwiseOr         (r(0)?, n(1)?, n(2)?),
+            14 => Instruction::BitwiseNot        (r(0)?, n(1)?),
+            15 => Instruction::MemoryRead        (r(0)?, n(1)?),
+            16 => Instruction::MemoryWrite       (n(0)?, n(1)?),
+            17 => Instruction::FunctionCall      (n(0)?),
+            18 => Instruction::FunctionReturn,
+            19 => Instruction::PrintChar         (n(0)?),
+            20 => Instruction::ReadChar          (r(0)?),
+            21 => Instruction::NoOp,
+            op => Instruction::Unknown(op)
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct VM {
     pc: u16,
     breakpoints: HashSet<u16>,
+    call_trace: Option<BTreeSet<(u16, u16)>>,
+    captured_output: Option<String>,
+    halted: bool,
     input_buf: VecDeque<u16>,
     interrupted: bool,
     output_enabled: bool,
     memory: [u16; MAX_SIZE],
     registers: [u16; 8],
-    stack: Vec<u16>
+    stack: Vec<u16>,
+    steps_left: Option<usize>
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VM {
@@ -62,12 +158,16 @@ impl VM {
         Self {
             pc: 0,
             breakpoints: HashSet::new(),
+            call_trace: None,
+            captured_output: None,
+            halted: false,
             input_buf: VecDeque::new(),
             interrupted: false,
             output_enabled: true,
             memory: [0; MAX_SIZE],
             registers: [0; 8],
-            stack: vec![]
+            stack: vec![],
+            steps_left: None
         }
     }
 
@@ -75,6 +175,14 @@ impl VM {
         self.breakpoints.insert(position);
     }
 
+    pub fn dbg_get_breakpoints(&self) -> &HashSet<u16> {
+        &self.breakpoints
+    }
+
+    pub fn dbg_get_call_trace(&self) -> Option<&BTreeSet<(u16, u16)>> {
+        self.call_trace.as_ref()
+    }
+
     pub fn dbg_get_memory(&self) -> &[u16] {
         &self.memory
     }
@@ -83,6 +191,36 @@ impl VM {
         self.pc
     }
 
+    pub fn dbg_get_registers(&self) -> &[u16; 8] {
+        &self.registers
+    }
+
+    pub fn dbg_get_stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    // Instructions left before the execution gets interrupted, reaching 0 once it did
+    pub fn dbg_get_steps_left(&self) -> Option<usize> {
+        self.steps_left
+    }
+
+    pub fn dbg_remove_breakpoint(&mut self, position: u16) {
+        self.breakpoints.remove(&position);
+    }
+
+    pub fn dbg_set_call_tracing(&mut self, enabled: bool) {
+        self.call_trace = enabled.then(BTreeSet::new);
+    }
+
+    // Starts capturing the printed chars, or stops it returning what was captured
+    pub fn dbg_set_output_capture(&mut self, enabled: bool) -> Option<String> {
+        std::mem::replace(&mut self.captured_output, enabled.then(String::new))
+    }
+
+    pub fn dbg_take_captured_output(&mut self) -> String {
+        self.captured_output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     pub fn dbg_set_memory(&mut self, position: usize, value: u16) {
         self.memory[position] = value;
     }
@@ -91,10 +229,23 @@ impl VM {
         self.output_enabled = enabled;
     }
 
+    pub fn dbg_set_pc(&mut self, position: u16) {
+        self.pc = position;
+    }
+
+    // Interrupts the execution after the given number of instructions
+    pub fn dbg_set_step_limit(&mut self, steps: Option<usize>) {
+        self.steps_left = steps;
+    }
+
     pub fn dbg_set_register(&mut self, register: usize, value: u16) {
         self.registers[register] = value;
     }
 
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
     pub fn load_binary(&mut self, file_path: &str) {
         fs::read(file_path)
             .unwrap_or_else(|_| panic!("{} should be a readable file", file_path))
@@ -104,40 +255,30 @@ impl VM {
             .for_each(|(idx, value)| self.memory[idx] = value);
     }
 
+    pub fn load_image(&mut self, image: &[u16]) {
+        self.memory.iter_mut().zip(image).for_each(|(word, &value)| *word = value);
+    }
+
+    // Applies the patch only if every word it changes has its original value
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<(), String> {
+        patch.check(&self.memory)?;
+        patch.changes.iter().for_each(|change| self.memory[change.address as usize] = change.patched);
+        Ok(())
+    }
+
     pub fn input_command(&mut self, command: &str) {
         self.input_buf.extend(command.chars().map(|c| c as u16));
     }
 
     pub fn next_instruction(&mut self) -> Instruction {
-        match self.next_word() {
-            0  => Instruction::Halt,
-            1  => Instruction::SetRegister       (self.next_register(), self.next_number()),
-            2  => Instruction::Push              (self.next_number()),
-            3  => Instruction::Pop               (self.next_register()),
-            4  => Instruction::CompareEquals     (self.next_register(), self.next_number(), self.next_number()),
-            5  => Instruction::CompareGreaterThan(self.next_register(), self.next_number(), self.next_number()),
-            6  => Instruction::Jump              (self.next_number()),
-            7  => Instruction::JumpIfTrue        (self.next_number(),   self.next_number()),
-            8  => Instruction::JumpIfFalse       (self.next_number(),   self.next_number()),
-            9  => Instruction::Add               (self.next_register(), self.next_number(), self.next_number()),
-            10 => Instruction::Multiply          (self.next_register(), self.next_number(), self.next_number()),
-            11 => Instruction::Mod               (self.next_register(), self.next_number(), self.next_number()),
-            12 => Instruction::BitwiseAnd        (self.next_register(), self.next_number(), self.next_number()),
-            13 => Instruction::BitwiseOr         (self.next_register(), self.next_number(), self.next_number()),
-            14 => Instruction::BitwiseNot        (self.next_register(), self.next_number()),
-            15 => Instruction::MemoryRead        (self.next_register(), self.next_number()),
-            16 => Instruction::MemoryWrite       (self.next_number(),   self.next_number()),
-            17 => Instruction::FunctionCall      (self.next_number()),
-            18 => Instruction::FunctionReturn,
-            19 => Instruction::PrintChar         (self.next_number()),
-            20 => Instruction::ReadChar          (self.next_register()),
-            21 => Instruction::NoOp,
-            op => Instruction::Unknown(op)
-        }
+        let instruction = Instruction::decode(&self.memory, self.pc);
+
+        self.pc += instruction.size();
+        instruction
     }
 
     pub fn run(&mut self) {
-        self.interrupted = false;
+        self.interrupted = self.halted;
 
         while !self.interrupted {
             match &self.next_instruction() {
@@ -149,7 +290,7 @@ impl VM {
                 Instruction::CompareGreaterThan(a, b, c) => self.perform_compare_greater_than(a, b, c),
                 Instruction::FunctionCall(a)             => self.perform_function_call(a),
                 Instruction::FunctionReturn              => self.perform_function_return(),
-                Instruction::Halt                        => break,
+                Instruction::Halt                        => self.halted = true,
                 Instruction::Jump(a)                     => self.perform_jump(a),
                 Instruction::JumpIfFalse(a, b)           => self.perform_jump_if_false(a, b),
                 Instruction::JumpIfTrue(a, b)            => self.perform_jump_if_true(a, b),
@@ -166,23 +307,13 @@ impl VM {
                 Instruction::Unknown(opcode)             => panic!("Invalid opcode {}", opcode)
             }
 
-            self.interrupted |= self.breakpoints.contains(&self.pc);
-        }
-    }
-
-    fn next_number(&mut self) -> Number {
-        Number::from(self.next_word())
-    }
-
-    fn next_register(&mut self) -> Register {
-        Register::from(self.next_word() - 32768)
-    }
-
-    fn next_word(&mut self) -> u16 {
-        let word = self.memory[self.pc as usize];
+            if let Some(steps_left) = self.steps_left.as_mut() {
+                *steps_left = steps_left.saturating_sub(1);
+                self.interrupted |= *steps_left == 0;
+            }
 
-        self.pc += 1;
-        word
+            self.interrupted |= self.halted || self.breakpoints.contains(&self.pc);
+        }
     }
 
     fn perform_add(&mut self, a: &Register, b: &Number, c: &Number) {
@@ -210,6 +341,12 @@ impl VM {
     }
 
     fn perform_function_call(&mut self, a: &Number) {
+        let target = self.resolve_number(a);
+
+        if let Some(call_trace) = self.call_trace.as_mut() {
+            call_trace.insert((self.pc - 2, target));
+        }
+
         self.stack.push(self.pc);
         self.perform_jump(a);
     }
@@ -246,17 +383,24 @@ impl VM {
         self.registers[*a] = self.resolve_number(b) % self.resolve_number(c);
     }
 
+    // Products of 15-bit values don't fit in 16 bits
     fn perform_multiply(&mut self, a: &Register, b: &Number, c: &Number) {
-        self.registers[*a] = (self.resolve_number(b) * self.resolve_number(c)) % MAX_SIZE as u16;
+        self.registers[*a] = (self.resolve_number(b) as u32 * self.resolve_number(c) as u32 % MAX_SIZE as u32) as u16;
     }
 
     fn perform_pop(&mut self, a: &Register) {
         self.registers[*a] = self.stack.pop().unwrap();
     }
 
-    fn perform_print_char(&self, a: &Number) {
+    fn perform_print_char(&mut self, a: &Number) {
+        let c = self.resolve_number(a) as u8 as char;
+
         if self.output_enabled {
-            print!("{}", self.resolve_number(a) as u8 as char);
+            print!("{}", c);
+        }
+
+        if let Some(captured_output) = self.captured_output.as_mut() {
+            captured_output.push(c);
         }
     }
 
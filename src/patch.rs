@@ -0,0 +1,155 @@
+use std::{fmt::{self, Display, Formatter}, fs, str::FromStr};
+use crate::MAX_SIZE;
+
+/*
+Patches are changes to single words of the memory image, along with the value the word had originally, so they can be
+reported, applied through the debugger with `$ set_memory <address> <value>` or written to a new binary.
+
+Patch files hold a header line and a change per line, as the address and the original and patched values of the word,
+with empty lines and lines starting with # ignored:
+
+    synacor-patch 1
+    # teleporter bypass
+    5473: 8 -> 1
+    5475: 5627 -> 25734
+
+A patch is only applied if every word has its original value, so it refuses to apply to a different binary (or to the
+same one twice). Words beyond the end of a binary are taken as 0, as they are once loaded in memory.
+*/
+
+const HEADER: &str = "synacor-patch 1";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub address: u16,
+    pub original: u16,
+    pub patched: u16
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Patch {
+    pub changes: Vec<Change>
+}
+
+// Changes turning the words of the image from the address onwards into the given ones, leaving out the ones that match
+pub fn changes(image: &[u16], address: u16, words: &[u16]) -> Vec<Change> {
+    words.iter()
+        .enumerate()
+        .map(|(idx, &patched)| Change { address: address + idx as u16, original: image[address as usize + idx], patched })
+        .filter(|change| change.original != change.patched)
+        .collect()
+}
+
+pub fn read_image(file_path: &str) -> Result<Vec<u16>, String> {
+    let bytes = fs::read(file_path).map_err(|_| format!("{} should be a readable file", file_path))?;
+
+    Ok(bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect())
+}
+
+pub fn write_image(file_path: &str, image: &[u16]) -> Result<(), String> {
+    let bytes: Vec<u8> = image.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+    fs::write(file_path, bytes).map_err(|_| format!("Unable to write {}", file_path))
+}
+
+impl Patch {
+    // Changes turning the original image into the patched one
+    pub fn diff(original: &[u16], patched: &[u16]) -> Self {
+        let word = |image: &[u16], address: usize| image.get(address).copied().unwrap_or(0);
+        let changes = (0 .. original.len().max(patched.len()))
+            .map(|address| Change { address: address as u16, original: word(original, address), patched: word(patched, address) })
+            .filter(|change| change.original != change.patched)
+            .collect();
+
+        Self { changes }
+    }
+
+    pub fn read(file_path: &str) -> Result<Self, String> {
+        fs::read_to_string(file_path).map_err(|_| format!("{} should be a readable file", file_path))?.parse()
+    }
+
+    // Words of the image which don't have their original value
+    pub fn check(&self, image: &[u16]) -> Result<(), String> {
+        let mismatches: Vec<String> = self.changes
+            .iter()
+            .filter_map(|change| {
+                let value = image.get(change.address as usize).copied().unwrap_or(0);
+
+                (value != change.original).then(|| format!("{} is {} instead of {}", change.address, value, change.original))
+            })
+            .collect();
+
+        match mismatches[..] {
+            [] => Ok(()),
+            _  => Err(format!("the patch doesn't apply: {}", mismatches.join(", ")))
+        }
+    }
+
+    pub fn apply(&self, image: &mut Vec<u16>) -> Result<(), String> {
+        self.check(image)?;
+
+        for change in &self.changes {
+            if image.len() <= change.address as usize {
+                image.resize(change.address as usize + 1, 0);
+            }
+
+            image[change.address as usize] = change.patched;
+        }
+
+        Ok(())
+    }
+
+    pub fn to_commands(&self) -> Vec<String> {
+        self.changes.iter().map(|change| format!("$ set_memory {} {}", change.address, change.patched)).collect()
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.address, self.original, self.patched)
+    }
+}
+
+impl FromStr for Change {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, String> {
+        let error = || format!("{:?} should be <address>: <original> -> <patched>", line);
+        let (address, values) = line.split_once(':').ok_or_else(error)?;
+        let (original, patched) = values.split_once("->").ok_or_else(error)?;
+        let number = |value: &str| value.trim().parse::<u16>().map_err(|_| error());
+        let address = number(address)?;
+
+        if address as usize >= MAX_SIZE {
+            return Err(format!("{} is beyond the memory", address));
+        }
+
+        Ok(Self { address, original: number(original)?, patched: number(patched)? })
+    }
+}
+
+impl Display for Patch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Patch {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        if lines.next() != Some(HEADER) {
+            return Err(format!("a patch should start with {:?}", HEADER));
+        }
+
+        Ok(Self { changes: lines.map(str::parse).collect::<Result<Vec<Change>, String>>()? })
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt::{self, Display, Formatter};
+use crate::{debugger::Debugger, VM};
+
+/*
+Input scripts are replayed headless through the debugger, line by line until the end of the script or a `$ exit`, and
+their transcript is everything that got printed: the output of the game along with the output of the debugger commands.
+
+Transcripts are compared line by line against the golden ones recorded from a known good run, reporting the first line
+that differs (or is missing on either side).
+*/
+
+pub struct Difference {
+    pub line: usize,
+    pub expected: Option<String>,
+    pub actual: Option<String>
+}
+
+pub fn replay(vm: VM, script: &str) -> String {
+    let mut debugger = Debugger::new(vm);
+    let mut transcript = String::new();
+
+    for line in script.lines() {
+        match debugger.execute(&format!("{}\n", line)) {
+            Some(output) => transcript.push_str(&output),
+            None         => break
+        }
+    }
+
+    transcript
+}
+
+pub fn compare(expected: &str, actual: &str) -> Option<Difference> {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+
+    for line in 1 .. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (expected, actual) if expected != actual => {
+                return Some(Difference { line, expected: expected.map(String::from), actual: actual.map(String::from) });
+            }
+            _ => ()
+        }
+    }
+
+    None
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let describe = |line: &Option<String>| line.as_ref().map_or("end of transcript".to_string(), |line| format!("{:?}", line));
+
+        writeln!(f, "transcripts differ at line {}", self.line)?;
+        writeln!(f, "    expected: {}", describe(&self.expected))?;
+        write!(f, "    actual:   {}", describe(&self.actual))
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::world::{World, INVENTORY};
+
+/*
+Routes are the shortest sequences of exits between locations (breadth first search), which are the same commands that
+the player has to type. Synthetic exits aren't followed since they need some action other than a move, but forced moves
+(a location whose enter fn always moves the player somewhere else) are taken into account when arriving to a location.
+
+A plan is a sequence of steps done in order: going to a location, taking an item or using an item wherever the player
+is. The planner follows where the items are as the plan goes, and gets the items each step needs first:
+
+    Guards:  locations needing an item in the inventory to be entered safely (such as the lit lantern in the dark
+             passages), which routes avoid until the item is in the inventory and then go through
+    Uses:    items moving other items when used, needing some items in the inventory (such as the can filling the
+             empty lantern), so an item that isn't in any location is got by using the one moving it to the inventory
+
+Items are taken from their location before being used, which the route gets to like any other. The guards and uses are
+told to the planner (see discovery.rs for the ones found on the game), and conditions on anything else than items (such
+as the door of the ruins opening once the coins are placed) have to be handled by the steps given.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Location(usize),
+    Item(usize)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Go(usize),
+    Take(usize),
+    Use(usize)
+}
+
+struct Usage {
+    needs: Vec<usize>,
+    moves: Vec<(usize, usize)>
+}
+
+pub struct Planner<'a> {
+    world: &'a World,
+    forced_moves: HashMap<usize, usize>,
+    guards: HashMap<usize, usize>,
+    uses: BTreeMap<usize, Usage>
+}
+
+// State of the game as the plan goes, with the items being got to avoid needing one to get itself
+#[derive(Clone)]
+struct Progress {
+    commands: Vec<String>,
+    current: usize,
+    places: HashMap<usize, usize>,
+    getting: Vec<usize>
+}
+
+// Finds a location or item by id or name, items first since their names are unique
+pub fn resolve(world: &World, name: &str) -> Result<Target, String> {
+    if let Ok(id) = name.parse() {
+        return match (world.location(id), world.item(id)) {
+            (Some(_), _)    => Ok(Target::Location(id)),
+            (None, Some(_)) => Ok(Target::Item(id)),
+            (None, None)    => Err(format!("{} is neither a location nor an item", id))
+        };
+    }
+
+    if let Some(item) = world.items.iter().find(|item| item.name.eq_ignore_ascii_case(name)) {
+        return Ok(Target::Item(item.id));
+    }
+
+    let locations: Vec<usize> = world.locations
+        .iter()
+        .filter(|location| location.name.eq_ignore_ascii_case(name))
+        .map(|location| location.id)
+        .collect();
+
+    match locations[..] {
+        []   => Err(format!("{} is neither a location nor an item", name)),
+        [id] => Ok(Target::Location(id)),
+        _    => {
+            let ids: Vec<String> = locations.iter().map(|id| id.to_string()).collect();
+
+            Err(format!("{} is ambiguous, use one of the location ids {}", name, ids.join(", ")))
+        }
+    }
+}
+
+// Commands of a plan in the format of the input scripts of the debugger
+pub fn to_script(commands: &[String]) -> String {
+    let mut script: String = commands.iter().map(|command| format!("{}\n", command)).collect();
+
+    script.push_str("$ exit\n");
+    script
+}
+
+impl Progress {
+    fn holds(&self, item: usize) -> bool {
+        self.places.get(&item) == Some(&INVENTORY)
+    }
+}
+
+impl<'a> Planner<'a> {
+    pub fn new(world: &'a World) -> Self {
+        Self { world, forced_moves: HashMap::new(), guards: HashMap::new(), uses: BTreeMap::new() }
+    }
+
+    pub fn forced_move(mut self, from: usize, to: usize) -> Self {
+        self.forced_moves.insert(from, to);
+        self
+    }
+
+    pub fn guard(mut self, location: usize, item: usize) -> Self {
+        self.guards.insert(location, item);
+        self
+    }
+
+    // Using the item moves each of the given items to its location, once the needed ones are in the inventory
+    pub fn item_use(mut self, item: usize, needs: &[usize], moves: &[(usize, usize)]) -> Self {
+        self.uses.insert(item, Usage { needs: needs.to_vec(), moves: moves.to_vec() });
+        self
+    }
+
+    fn arrive(&self, location: usize) -> usize {
+        self.forced_moves.get(&location).copied().unwrap_or(location)
+    }
+
+    // Commands along with the location each one arrives to, without going through the avoided locations
+    pub fn route(&self, from: usize, to: usize, avoided: impl Fn(usize) -> bool) -> Option<Vec<(&'a str, usize)>> {
+        let (from, to) = (self.arrive(from), self.arrive(to));
+        let mut previous: HashMap<usize, (usize, &'a str)> = HashMap::new();
+        let mut pending = VecDeque::from([from]);
+
+        while let Some(location_id) = pending.pop_front() {
+            if location_id == to {
+                let mut commands = vec![];
+                let mut current = to;
+
+                while current != from {
+                    let (before, command) = previous[&current];
+
+                    commands.push((command, current));
+                    current = before;
+                }
+
+                commands.reverse();
+                return Some(commands);
+            }
+
+            let exits = self.world.location(location_id).into_iter().flat_map(|location| &location.connections);
+
+            for exit in exits.filter(|exit| !exit.synthetic) {
+                let next = self.arrive(exit.target);
+
+                if next != from && !previous.contains_key(&next) && !avoided(next) {
+                    previous.insert(next, (location_id, exit.label.as_str()));
+                    pending.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Goes around the guarded locations until there's no other way, getting the item of the first one on the route then
+    fn walk(&self, progress: &mut Progress, to: usize) -> Result<(), String> {
+        let mut guarded = vec![];
+
+        loop {
+            let unmet_guard = |location: usize| self.guards.get(&location).copied().filter(|&item| !progress.holds(item));
+
+            if let Some(route) = self.route(progress.current, to, |location| unmet_guard(location).is_some()) {
+                progress.commands.extend(route.into_iter().map(|(command, _)| command.to_string()));
+                progress.current = self.arrive(to);
+                return Ok(());
+            }
+
+            let route = self.route(progress.current, to, |_| false).ok_or(format!("{} can't be reached from {}", to, progress.current))?;
+            let item = route.iter().find_map(|&(_, location)| unmet_guard(location)).unwrap();
+
+            if guarded.contains(&item) {
+                return Err(format!("{} can't be kept in the inventory on the way to {}", self.world.item(item).unwrap().name, to));
+            }
+
+            guarded.push(item);
+            self.get(progress, item)?;
+        }
+    }
+
+    // Takes the item from its location or uses the item moving it to the inventory
+    fn get(&self, progress: &mut Progress, id: usize) -> Result<(), String> {
+        let item = self.world.item(id).ok_or(format!("{} is not an item", id))?;
+
+        if progress.holds(id) {
+            return Ok(());
+        }
+
+        if progress.getting.contains(&id) {
+            return Err(format!("{} is needed to get itself", item.name));
+        }
+
+        progress.getting.push(id);
+
+        match progress.places[&id] {
+            location if self.world.location(location).is_some() => {
+                self.walk(progress, location)?;
+                progress.commands.push(format!("take {}", item.name));
+                progress.places.insert(id, INVENTORY);
+            }
+            // Every item moving it to the inventory is tried in turn, on its own copy of the progress
+            _ => {
+                let mut attempt = Err(format!("{} isn't in any reachable location", item.name));
+
+                for (&used, _) in self.uses.iter().filter(|(_, usage)| usage.moves.contains(&(id, INVENTORY))) {
+                    let mut tried = progress.clone();
+
+                    attempt = self.use_item(&mut tried, used).map(|_| tried);
+
+                    if attempt.is_ok() {
+                        break;
+                    }
+                }
+
+                *progress = attempt?;
+            }
+        }
+
+        progress.getting.pop();
+        Ok(())
+    }
+
+    fn use_item(&self, progress: &mut Progress, id: usize) -> Result<(), String> {
+        let needs = self.uses.get(&id).map_or(&[][..], |usage| &usage.needs);
+
+        for &needed in needs.iter().chain([&id]) {
+            self.get(progress, needed)?;
+        }
+
+        progress.commands.push(format!("use {}", self.world.item(id).unwrap().name));
+
+        for &(item, location) in self.uses.get(&id).map_or(&[][..], |usage| &usage.moves) {
+            progress.places.insert(item, location);
+        }
+
+        Ok(())
+    }
+
+    pub fn plan(&self, from: usize, steps: &[Step]) -> Result<Vec<String>, String> {
+        let mut progress = Progress {
+            commands: vec![],
+            current: self.arrive(from),
+            places: self.world.items.iter().map(|item| (item.id, item.location)).collect(),
+            getting: vec![]
+        };
+
+        for &step in steps {
+            match step {
+                Step::Go(id)   => self.walk(&mut progress, id)?,
+                Step::Take(id) => self.get(&mut progress, id)?,
+                Step::Use(id)  => self.use_item(&mut progress, id)?
+            }
+        }
+
+        Ok(progress.commands)
+    }
+}
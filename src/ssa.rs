@@ -0,0 +1,296 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt::{self, Display, Formatter}};
+use crate::{dataflow::{register_defs, register_uses, Liveness, Summaries, REGISTERS}, ir::{join, Function, Stmt}, Register};
+
+/*
+SSA construction for the registers of a function, following Cytron et al.:
+
+    1. Compute the immediate dominators (Cooper, Harvey & Kennedy) and the dominance frontiers
+    2. Place phi nodes on the iterated dominance frontier of the blocks defining each register,
+       pruned to the blocks where the register is live
+    3. Rename by walking the dominator tree, version 0 being the value on entry to the function
+
+Calls use and define registers according to the callee summaries.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Value {
+    pub register: Register,
+    pub version: u32
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "r{}.{}", self.register, self.version)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Phi {
+    pub target: Value,
+    pub sources: Vec<(u16, Value)>
+}
+
+#[derive(Clone, Debug)]
+pub struct SsaStmt {
+    pub stmt: Stmt,
+    pub uses: BTreeMap<Register, Value>,
+    pub defs: BTreeMap<Register, Value>
+}
+
+#[derive(Clone, Debug)]
+pub struct SsaBlock {
+    pub start: u16,
+    pub phis: Vec<Phi>,
+    pub stmts: Vec<SsaStmt>,
+    pub successors: Vec<u16>
+}
+
+#[derive(Clone, Debug)]
+pub struct SsaFunction {
+    pub entry: u16,
+    pub blocks: BTreeMap<u16, SsaBlock>
+}
+
+pub fn reverse_postorder(function: &Function) -> Vec<u16> {
+    let mut visited = BTreeSet::from([function.entry]);
+    let mut postorder = vec![];
+    let mut pending = vec![(function.entry, 0)];
+
+    while let Some((start, idx)) = pending.pop() {
+        match function.blocks[&start].successors.get(idx) {
+            Some(successor) => {
+                pending.push((start, idx + 1));
+
+                if visited.insert(*successor) {
+                    pending.push((*successor, 0));
+                }
+            }
+            None => postorder.push(start)
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
+
+pub fn immediate_dominators(function: &Function) -> BTreeMap<u16, u16> {
+    let order = reverse_postorder(function);
+    let position: BTreeMap<u16, usize> = order.iter().enumerate().map(|(idx, &b)| (b, idx)).collect();
+    let predecessors = function.predecessors();
+    let mut idom = BTreeMap::from([(function.entry, function.entry)]);
+    let mut changed = true;
+
+    let intersect = |idom: &BTreeMap<u16, u16>, mut a: u16, mut b: u16| {
+        while a != b {
+            while position[&a] > position[&b] { a = idom[&a]; }
+            while position[&b] > position[&a] { b = idom[&b]; }
+        }
+        a
+    };
+
+    while changed {
+        changed = false;
+
+        for &block in order.iter().skip(1) {
+            let mut processed = predecessors[&block].iter().filter(|p| idom.contains_key(p));
+            let Some(&first) = processed.next() else { continue };
+            let new_idom = processed.fold(first, |acc, &p| intersect(&idom, acc, p));
+
+            if idom.get(&block) != Some(&new_idom) {
+                idom.insert(block, new_idom);
+                changed = true;
+            }
+        }
+    }
+
+    idom
+}
+
+pub fn dominance_frontiers(function: &Function, idom: &BTreeMap<u16, u16>) -> BTreeMap<u16, BTreeSet<u16>> {
+    let mut frontiers: BTreeMap<u16, BTreeSet<u16>> = function.blocks.keys().map(|&b| (b, BTreeSet::new())).collect();
+
+    for (block, predecessors) in function.predecessors() {
+        if predecessors.len() < 2 || !idom.contains_key(&block) {
+            continue;
+        }
+
+        for mut runner in predecessors.into_iter().filter(|p| idom.contains_key(p)) {
+            while runner != idom[&block] {
+                frontiers.entry(runner).or_default().insert(block);
+                runner = idom[&runner];
+            }
+        }
+    }
+
+    frontiers
+}
+
+impl SsaFunction {
+    pub fn build(function: &Function, summaries: &Summaries) -> Self {
+        let idom = immediate_dominators(function);
+        let frontiers = dominance_frontiers(function, &idom);
+        let liveness = Liveness::compute(function, summaries, &BTreeSet::new());
+        let mut phi_registers: BTreeMap<u16, BTreeSet<Register>> = BTreeMap::new();
+
+        for register in 0 .. REGISTERS {
+            let mut pending: Vec<u16> = function.blocks
+                .values()
+                .filter(|block| block.stmts.iter().any(|stmt| register_defs(stmt, summaries).contains(&register)))
+                .map(|block| block.start)
+                .collect();
+            let mut placed = BTreeSet::new();
+
+            while let Some(block) = pending.pop() {
+                for &frontier in &frontiers[&block] {
+                    if liveness.live_in[&frontier].contains(&register) && placed.insert(frontier) {
+                        phi_registers.entry(frontier).or_default().insert(register);
+                        pending.push(frontier);
+                    }
+                }
+            }
+        }
+
+        let mut children: BTreeMap<u16, Vec<u16>> = BTreeMap::new();
+
+        for (&block, &dominator) in &idom {
+            if block != dominator {
+                children.entry(dominator).or_default().push(block);
+            }
+        }
+
+        let mut renamer = Renamer {
+            summaries,
+            stacks: std::array::from_fn(|_| vec![0]),
+            counters: [0; REGISTERS],
+            blocks: function.blocks
+                .values()
+                .map(|block| (block.start, SsaBlock {
+                    start: block.start,
+                    phis: vec![],
+                    stmts: vec![],
+                    successors: block.successors.clone()
+                }))
+                .collect()
+        };
+
+        for (block, registers) in &phi_registers {
+            for &register in registers {
+                renamer.blocks.get_mut(block).unwrap().phis.push(Phi { target: Value { register, version: 0 }, sources: vec![] });
+            }
+        }
+
+        renamer.rename(function, &children, function.entry);
+        renamer.blocks.retain(|block, _| idom.contains_key(block));
+
+        Self { entry: function.entry, blocks: renamer.blocks }
+    }
+}
+
+struct Renamer<'a> {
+    summaries: &'a Summaries,
+    stacks: [Vec<u32>; REGISTERS],
+    counters: [u32; REGISTERS],
+    blocks: BTreeMap<u16, SsaBlock>
+}
+
+impl Renamer<'_> {
+    fn define(&mut self, register: Register) -> Value {
+        self.counters[register] += 1;
+        self.stacks[register].push(self.counters[register]);
+        Value { register, version: self.counters[register] }
+    }
+
+    fn current(&self, register: Register) -> Value {
+        Value { register, version: *self.stacks[register].last().unwrap() }
+    }
+
+    fn rename(&mut self, function: &Function, children: &BTreeMap<u16, Vec<u16>>, start: u16) {
+        let saved: Vec<usize> = self.stacks.iter().map(|stack| stack.len()).collect();
+        let block = &function.blocks[&start];
+        let phi_registers: Vec<Register> = self.blocks[&start].phis.iter().map(|phi| phi.target.register).collect();
+
+        for (idx, register) in phi_registers.into_iter().enumerate() {
+            let target = self.define(register);
+
+            self.blocks.get_mut(&start).unwrap().phis[idx].target = target;
+        }
+
+        for stmt in &block.stmts {
+            let uses = register_uses(stmt, self.summaries).into_iter().map(|r| (r, self.current(r))).collect();
+            let defs = register_defs(stmt, self.summaries).into_iter().map(|r| (r, self.define(r))).collect();
+
+            self.blocks.get_mut(&start).unwrap().stmts.push(SsaStmt { stmt: *stmt, uses, defs });
+        }
+
+        for successor in &block.successors {
+            let sources: Vec<(usize, Value)> = self.blocks[successor].phis
+                .iter()
+                .enumerate()
+                .map(|(idx, phi)| (idx, self.current(phi.target.register)))
+                .collect();
+
+            for (idx, value) in sources {
+                self.blocks.get_mut(successor).unwrap().phis[idx].sources.push((start, value));
+            }
+        }
+
+        for &child in children.get(&start).into_iter().flatten() {
+            self.rename(function, children, child);
+        }
+
+        for (stack, len) in self.stacks.iter_mut().zip(saved) {
+            stack.truncate(len);
+        }
+    }
+}
+
+impl Display for SsaStmt {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.stmt.address)?;
+        self.stmt.op.fmt_with(f, &|register, is_def| {
+            let values = if is_def { &self.defs } else { &self.uses };
+
+            values.get(&register).map_or(format!("r{}", register), |value| value.to_string())
+        })?;
+
+        let implicit: Vec<String> = self.uses
+            .values()
+            .filter(|value| !self.stmt.op.register_uses().contains(&value.register))
+            .map(|value| format!("uses {}", value))
+            .chain(self.defs
+                .values()
+                .filter(|value| self.stmt.op.register_def() != Some(value.register))
+                .map(|value| format!("defines {}", value)))
+            .collect();
+
+        if !implicit.is_empty() {
+            write!(f, "  ; {}", implicit.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for SsaFunction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "function {}", self.entry)?;
+
+        for block in self.blocks.values() {
+            writeln!(f, "  block {}", block.start)?;
+
+            for phi in &block.phis {
+                let sources = phi.sources.iter().map(|(block, value)| format!("{}: {}", block, value));
+
+                writeln!(f, "    {} = phi({})", phi.target, join(sources))?;
+            }
+
+            for stmt in &block.stmts {
+                writeln!(f, "    {}", stmt)?;
+            }
+
+            writeln!(f, "    -> {}", join(&block.successors))?;
+        }
+
+        Ok(())
+    }
+}
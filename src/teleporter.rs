@@ -0,0 +1,384 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+    ops::RangeInclusive,
+    sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Mutex},
+    thread,
+    time::{Duration, Instant}
+};
+use crate::{
+    codes::{extract_codes, Code},
+    dataflow::{Program, REGISTERS},
+    discovery::{Layout, TeleporterCheck},
+    patch::{changes, Patch},
+    world::{get_items, move_item, INVENTORY},
+    Instruction, Number, Register, VM, MAX_SIZE
+};
+
+/*
+The teleporter check calls a recursive function with fixed r0 and r1 and compares its result with an expected value,
+where the function also reads r7 and is far too slow to run as is. Instead of relying on a closed form of the function,
+the check is emulated from its entry point with every call memoized on (pc, r0, r1, r7): the first time a call returns,
+the registers written by the callee are recorded, and later calls with the same key just set them and skip the call.
+
+This only holds if the functions reached from the entry point read no other registers (which is verified on their
+summaries) and don't touch the memory or the I/O (which makes the emulation fail).
+
+The check can also be evaluated faster with a closed form derived by hand for f(4, 1, r7) in the original binary, which
+a variant of the function called with the same arguments wouldn't follow, so the r7 it gives should be confirmed through
+the emulation. The energy level verification function of the original binary is the following (with every operation
+taken modulo 32768):
+
+    f(r₀, r₁, r₇) = f(n, x, y) = fₙ(x, y)
+        f₀(x, y) = x + 1
+        fₙ(0, y) = fₙ₋₁(y, y)
+        fₙ(x, y) = fₙ₋₁(fₙ(x - 1, y), y)
+
+Expanding the first levels, each of them by induction on x:
+
+    f₁(x, y) = x + y + 1
+    f₂(x, y) = f₁(f₂(x - 1, y), y) = f₂(x - 1, y) + y + 1   with f₂(0, y) = f₁(y, y) = 2 * y + 1
+             = x * (y + 1) + 2 * y + 1
+    f₃(x, y) = f₂(f₃(x - 1, y), y) = f₃(x - 1, y) * (y + 1) + 2 * y + 1   with f₃(0, y) = f₂(y, y) = y² + 3 * y + 1
+
+So f₃ is tabulated for every x from the previous one, and the check only needs two lookups in the table:
+
+    f₄(1, y) = f₃(f₄(0, y), y) = f₃(f₃(y, y), y)
+
+Either check is searched for the candidates giving the target value across threads, which take chunks of consecutive
+candidates until there are none left or, unless the search is exhaustive, until a solution is found. The progress is
+reported periodically from the calling thread with the number of candidates checked.
+
+Once r7 is known, the check can be bypassed by patching the use fn of the teleporter:
+
+    jf r7 <elsewhere>                            ->  set r7 <solution>
+    set r0 <a>; set r1 <b>; call <check>         ->  set r0 <expected>; noop (x5)
+
+so it doesn't depend on r7 being set beforehand (which makes the patch work on a binary too), and the result the
+comparison expects is set without calling the check. The r7 given is checked through the emulation before patching,
+since the code printed at the beach is decrypted with it, and the patch is verified by using the teleporter on a clone
+of the VM, which should take the player to the beach.
+*/
+
+const EMULATION_STEPS: usize = 100_000_000;
+const MEMO_REGISTERS: [Register; 3] = [0, 1, 7];
+
+const SET: u16 = 1;
+pub const JMP: u16 = 6;
+const NOOP: u16 = 21;
+const REGISTER_BASE: u16 = 32768;
+
+const CHUNK_SIZE: usize = 64;
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+type Key = (u16, u16, u16, u16);
+
+// The memo is hit for nearly every step of the emulation, so its keys get a cheap multiplicative hash
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|&byte| self.write_u64(byte as u64));
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+type Memo = HashMap<Key, [u16; REGISTERS], BuildHasherDefault<KeyHasher>>;
+
+pub trait Check: Sync {
+    // Result of the check function for the given r7
+    fn evaluate(&self, r7: u16) -> Result<u16, String>;
+}
+
+pub struct FormulaCheck;
+
+pub struct EmulatedCheck {
+    memory: Vec<u16>,
+    entry: u16,
+    arguments: [u16; 2],
+    writes: HashMap<u16, Vec<Register>>
+}
+
+pub struct Search {
+    target: u16,
+    threads: usize,
+    exhaustive: bool
+}
+
+struct Frame {
+    key: Key,
+    stack_size: usize
+}
+
+fn resolve(registers: &[u16; REGISTERS], number: &Number) -> u16 {
+    match *number {
+        Number::Literal(value)  => value,
+        Number::Register(r)     => registers[r]
+    }
+}
+
+impl EmulatedCheck {
+    pub fn new(memory: &[u16], entry: u16, arguments: [u16; 2]) -> Result<Self, String> {
+        let program = Program::lift(memory, &[entry]);
+        let mut writes = HashMap::new();
+
+        for (&address, summary) in &program.summaries.functions {
+            if let Some(r) = summary.inputs.iter().find(|r| !MEMO_REGISTERS.contains(r)) {
+                return Err(format!("function {} reads r{}, calls can't be memoized on (pc, r0, r1, r7)", address, r));
+            }
+
+            writes.insert(address, summary.writes.iter().copied().collect());
+        }
+
+        Ok(Self { memory: memory.to_vec(), entry, arguments, writes })
+    }
+}
+
+impl Check for EmulatedCheck {
+    fn evaluate(&self, r7: u16) -> Result<u16, String> {
+        let mut registers = [0; REGISTERS];
+        let mut stack: Vec<u16> = vec![];
+        let mut frames: Vec<Frame> = vec![];
+        let mut memo = Memo::default();
+        let mut pc = self.entry;
+
+        registers[0] = self.arguments[0];
+        registers[1] = self.arguments[1];
+        registers[7] = r7;
+        frames.push(Frame { key: (self.entry, registers[0], registers[1], r7), stack_size: 0 });
+
+        for _ in 0 .. EMULATION_STEPS {
+            let instruction = Instruction::decode(&self.memory, pc);
+            let next = pc + instruction.size();
+
+            pc = next;
+
+            match &instruction {
+                Instruction::Add(a, b, c)                => registers[*a] = (resolve(&registers, b) + resolve(&registers, c)) % MAX_SIZE as u16,
+                Instruction::BitwiseAnd(a, b, c)         => registers[*a] = resolve(&registers, b) & resolve(&registers, c),
+                Instruction::BitwiseNot(a, b)            => registers[*a] = !resolve(&registers, b) % MAX_SIZE as u16,
+                Instruction::BitwiseOr(a, b, c)          => registers[*a] = resolve(&registers, b) | resolve(&registers, c),
+                Instruction::CompareEquals(a, b, c)      => registers[*a] = (resolve(&registers, b) == resolve(&registers, c)) as u16,
+                Instruction::CompareGreaterThan(a, b, c) => registers[*a] = (resolve(&registers, b) > resolve(&registers, c)) as u16,
+                Instruction::Jump(a)                     => pc = resolve(&registers, a),
+                Instruction::JumpIfFalse(a, b)           => if resolve(&registers, a) == 0 { pc = resolve(&registers, b) },
+                Instruction::JumpIfTrue(a, b)            => if resolve(&registers, a) != 0 { pc = resolve(&registers, b) },
+                Instruction::Mod(a, b, c)                => registers[*a] = resolve(&registers, b) % resolve(&registers, c),
+                Instruction::Multiply(a, b, c)           => {
+                    registers[*a] = (resolve(&registers, b) as u32 * resolve(&registers, c) as u32 % MAX_SIZE as u32) as u16;
+                }
+                Instruction::NoOp                        => (),
+                Instruction::Pop(a)                      => registers[*a] = stack.pop().ok_or("pop on an empty stack")?,
+                Instruction::Push(a)                     => stack.push(resolve(&registers, a)),
+                Instruction::SetRegister(a, b)           => registers[*a] = resolve(&registers, b),
+                Instruction::FunctionCall(a) => {
+                    let target = resolve(&registers, a);
+                    let key = (target, registers[0], registers[1], registers[7]);
+
+                    match memo.get(&key) {
+                        Some(result) => self.writes[&target].iter().for_each(|&r| registers[r] = result[r]),
+                        None => {
+                            if !self.writes.contains_key(&target) {
+                                return Err(format!("call to {} at {} wasn't found when lifting the check", target, next - 2));
+                            }
+
+                            stack.push(next);
+                            frames.push(Frame { key, stack_size: stack.len() });
+                            pc = target;
+                        }
+                    }
+                }
+                Instruction::FunctionReturn => {
+                    let frame = frames.pop().ok_or("return without a call")?;
+
+                    if stack.len() != frame.stack_size {
+                        return Err(format!("unbalanced stack when returning from {}", frame.key.0));
+                    }
+
+                    memo.insert(frame.key, registers);
+
+                    match stack.pop() {
+                        Some(address) => pc = address,
+                        None          => return Ok(registers[0])
+                    }
+                }
+                instruction => return Err(format!("{:?} at {} isn't supported by the emulation", instruction, next - instruction.size()))
+            }
+        }
+
+        Err(format!("the check didn't return after {} steps", EMULATION_STEPS))
+    }
+}
+
+impl FormulaCheck {
+    pub fn new(arguments: [u16; 2]) -> Result<Self, String> {
+        match arguments {
+            [4, 1] => Ok(Self),
+            [r0, r1] => Err(format!("the check is f({}, {}, r7), only f(4, 1, r7) is supported", r0, r1))
+        }
+    }
+}
+
+impl Check for FormulaCheck {
+    fn evaluate(&self, r7: u16) -> Result<u16, String> {
+        let y = r7 as u32;
+        let mut f3 = vec![0; MAX_SIZE];
+
+        f3[0] = (y * y + 3 * y + 1) % MAX_SIZE as u32;
+
+        for i in 1 .. f3.len() {
+            f3[i] = (f3[i - 1] * (y + 1) + 2 * y + 1) % MAX_SIZE as u32;
+        }
+
+        Ok(f3[f3[y as usize] as usize] as u16)
+    }
+}
+
+impl Search {
+    pub fn new(target: u16) -> Self {
+        Self { target, threads: thread::available_parallelism().map_or(1, |threads| threads.get()), exhaustive: false }
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn exhaustive(mut self, exhaustive: bool) -> Self {
+        self.exhaustive = exhaustive;
+        self
+    }
+
+    // Every r7 in the candidates for which the check gives the target value (or the ones found before stopping when
+    // the search isn't exhaustive), calling progress with the number of candidates checked out of the total
+    pub fn run(&self, check: &impl Check, candidates: RangeInclusive<u16>, progress: &mut dyn FnMut(usize, usize)) -> Result<Vec<u16>, String> {
+        let candidates: Vec<u16> = candidates.collect();
+        let next = AtomicUsize::new(0);
+        let checked = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let solutions = Mutex::new(vec![]);
+        let error = Mutex::new(None);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0 .. self.threads)
+                .map(|_| scope.spawn(|| {
+                    while !stop.load(Ordering::Relaxed) {
+                        let start = next.fetch_add(CHUNK_SIZE, Ordering::Relaxed);
+
+                        for &r7 in candidates.iter().skip(start).take(CHUNK_SIZE) {
+                            if stop.load(Ordering::Relaxed) {
+                                return;
+                            }
+
+                            match check.evaluate(r7) {
+                                Ok(value) if value == self.target => {
+                                    solutions.lock().unwrap().push(r7);
+                                    stop.store(!self.exhaustive, Ordering::Relaxed);
+                                }
+                                Ok(_)  => (),
+                                Err(e) => {
+                                    error.lock().unwrap().get_or_insert(e);
+                                    stop.store(true, Ordering::Relaxed);
+                                }
+                            }
+
+                            checked.fetch_add(1, Ordering::Relaxed);
+                        }
+
+                        if start + CHUNK_SIZE >= candidates.len() {
+                            return;
+                        }
+                    }
+                }))
+                .collect();
+
+            let mut reported = Instant::now();
+
+            while !workers.iter().all(|worker| worker.is_finished()) {
+                thread::sleep(POLL_INTERVAL);
+
+                if reported.elapsed() >= PROGRESS_INTERVAL {
+                    progress(checked.load(Ordering::Relaxed), candidates.len());
+                    reported = Instant::now();
+                }
+            }
+
+            progress(checked.load(Ordering::Relaxed), candidates.len());
+        });
+
+        match error.into_inner().unwrap() {
+            Some(error) => Err(error),
+            None        => {
+                let mut solutions = solutions.into_inner().unwrap();
+
+                solutions.sort();
+                Ok(solutions)
+            }
+        }
+    }
+}
+
+// Changes to the memory bypassing the teleporter check with the given r7, which is checked through the emulation
+pub fn bypass(memory: &[u16], check: &TeleporterCheck, r7: u16) -> Result<Patch, String> {
+    let value = EmulatedCheck::new(memory, check.function, check.arguments)?.evaluate(r7)?;
+
+    if value != check.expected {
+        return Err(format!("the check gives {} for r7 = {} instead of {}", value, r7, check.expected));
+    }
+
+    let arguments = check.call - 6;
+    let mut address = check.use_fn;
+    let guard = loop {
+        if check.use_fn == 0 || address >= arguments {
+            return Err(format!("no jf r7 found in the use fn {} before the check", check.use_fn));
+        }
+
+        match Instruction::decode(memory, address) {
+            Instruction::JumpIfFalse(Number::Register(7), _) => break address,
+            instruction                                       => address += instruction.size()
+        }
+    };
+    let mut patch = Patch { changes: changes(memory, guard, &[SET, REGISTER_BASE + 7, r7]) };
+
+    patch.changes.extend(changes(memory, arguments, &[SET, REGISTER_BASE, check.expected, NOOP, NOOP, NOOP, NOOP, NOOP]));
+    Ok(patch)
+}
+
+// Uses the teleporter from the inventory on a clone of the VM waiting for a command, with the patch applied, and
+// returns the codes announced if it took the player to the beach
+pub fn verify_bypass(vm: &VM, layout: &Layout, patch: &Patch) -> Result<Vec<Code>, String> {
+    let mut vm = vm.clone();
+    let items = get_items(vm.dbg_get_memory(), layout.items_table);
+    let teleporter = items
+        .iter()
+        .find(|item| item.use_fn != 0 && item.use_fn == layout.teleporter.use_fn)
+        .ok_or("no item uses the teleporter check")?;
+
+    vm.apply_patch(patch)?;
+    move_item(&mut vm, teleporter.id, INVENTORY);
+    vm.dbg_set_output_enabled(false);
+    vm.dbg_set_output_capture(true);
+    vm.input_command(&format!("use {}\n", teleporter.name));
+    vm.run();
+
+    let location = vm.dbg_get_memory()[layout.current_location as usize] as usize;
+
+    if location != layout.beach_location {
+        return Err(format!("the teleporter took the player to {} instead of the beach ({})", location, layout.beach_location));
+    }
+
+    Ok(extract_codes(&vm.dbg_take_captured_output()))
+}
@@ -0,0 +1,143 @@
+use std::{io::{stdin, stdout, Read, Write}, panic, process::{Command, Stdio}, thread};
+
+/*
+The terminal is switched to raw mode through `stty`, so the keys are read as they're typed, and restored once the raw
+mode is dropped, or before the message of a panic gets printed if it should restore on panic. Keys are decoded from the
+bytes read at once, since the escape sequences of the special keys arrive together:
+
+    ESC [ A / B / C / D            up / down / right / left
+    ESC [ H / F, ESC [ 1~ / 4~     home / end
+    ESC [ 3~ / 5~ / 6~             delete / page up / page down
+*/
+
+pub enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Interrupt,
+    EndOfFile,
+    Other
+}
+
+pub struct RawMode {
+    settings: String,
+    hooked: bool
+}
+
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .map_err(|_| "stty should be available to set up the terminal".to_string())?;
+
+    match output.status.success() {
+        true  => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => Err("stdin should be a terminal".to_string())
+    }
+}
+
+// Rows and columns of the terminal
+pub fn size() -> (usize, usize) {
+    stty(&["size"])
+        .ok()
+        .and_then(|size| {
+            let (rows, columns) = size.split_once(' ')?;
+
+            Some((rows.parse().ok()?, columns.parse().ok()?))
+        })
+        .unwrap_or((24, 80))
+}
+
+fn keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let (key, size) = match bytes[idx ..] {
+            [0x1b, b'[', b'A', ..]       => (Key::Up, 3),
+            [0x1b, b'[', b'B', ..]       => (Key::Down, 3),
+            [0x1b, b'[', b'C', ..]       => (Key::Right, 3),
+            [0x1b, b'[', b'D', ..]       => (Key::Left, 3),
+            [0x1b, b'[', b'H', ..]       => (Key::Home, 3),
+            [0x1b, b'[', b'F', ..]       => (Key::End, 3),
+            [0x1b, b'[', b'1', b'~', ..] => (Key::Home, 4),
+            [0x1b, b'[', b'3', b'~', ..] => (Key::Delete, 4),
+            [0x1b, b'[', b'4', b'~', ..] => (Key::End, 4),
+            [0x1b, b'[', b'5', b'~', ..] => (Key::PageUp, 4),
+            [0x1b, b'[', b'6', b'~', ..] => (Key::PageDown, 4),
+            [0x1b, b'[', ..]             => (Key::Other, bytes.len() - idx),
+            [0x1b, ..]                   => (Key::Escape, 1),
+            [b'\r' | b'\n', ..]          => (Key::Enter, 1),
+            [b'\t', ..]                  => (Key::Tab, 1),
+            [0x7f | 0x08, ..]            => (Key::Backspace, 1),
+            [0x01, ..]                   => (Key::Home, 1),
+            [0x03, ..]                   => (Key::Interrupt, 1),
+            [0x04, ..]                   => (Key::EndOfFile, 1),
+            [0x05, ..]                   => (Key::End, 1),
+            [byte @ 0x20 ..= 0x7e, ..]   => (Key::Char(byte as char), 1),
+            _                            => (Key::Other, 1)
+        };
+
+        keys.push(key);
+        idx += size;
+    }
+
+    keys
+}
+
+// Keys typed since the last read, waiting for some, or none once stdin is closed
+pub fn read_keys() -> Result<Vec<Key>, String> {
+    let mut buffer = [0; 64];
+    let read = stdin().read(&mut buffer).map_err(|_| "Unable to read the keys".to_string())?;
+
+    Ok(keys(&buffer[.. read]))
+}
+
+impl RawMode {
+    pub fn enable() -> Result<Self, String> {
+        let settings = stty(&["-g"])?;
+
+        stty(&["raw", "-echo"])?;
+        Ok(Self { settings, hooked: false })
+    }
+
+    // Restores the terminal along with the given escape sequences when panicking, so the message can be read
+    pub fn restore_on_panic(mut self, sequences: &'static str) -> Self {
+        let settings = self.settings.clone();
+        let hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            print!("{}", sequences);
+            stdout().flush().ok();
+            stty(&[&settings]).ok();
+            hook(info);
+        }));
+
+        self.hooked = true;
+        self
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stdout().flush().ok();
+        stty(&[&self.settings]).ok();
+
+        // Taking the hook puts back the default one
+        if self.hooked && !thread::panicking() {
+            drop(panic::take_hook());
+        }
+    }
+}
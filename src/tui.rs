@@ -0,0 +1,434 @@
+use std::{io::{stdout, Write}, rc::Rc, sync::mpsc, thread};
+use crate::{asm::{Listing, Syntax}, debugger::Debugger, terminal::{self, read_keys, Key, RawMode}, Instruction, MAX_SIZE};
+
+/*
+The terminal debugger draws the state of the VM full screen with ANSI escape sequences, the terminal being switched to
+raw mode (see terminal.rs) while it runs, and restored if it panics:
+
+    ┌ status: pc, whether the VM is paused, waiting for input or halted ───────────────────┐
+    │ disassembly around pc            │ registers, changed by the last action highlighted │
+    │ > pc, * breakpoints, cursor      │ stack, newest first, marking return addresses     │
+    │ memory from the given address, as hex words and chars                                │
+    │ output of the game, scrolling                                                        │
+    └ keys or input line ──────────────────────────────────────────────────────────────────┘
+
+Every action goes through the debugger commands, so the keys are shortcuts for them:
+
+    s               step: runs the instruction at pc
+    n               next: runs the instruction at pc, or the whole function if it's a call
+    c               continue: runs until a breakpoint, the game waits for input or the VM halts
+    up / down       moves the cursor of the disassembly
+    b               toggles the breakpoint at the cursor
+    m               shows the memory from the given address
+    pgup / pgdn     scrolls the memory
+    i / enter       writes a line for the game, or a debugger command starting with $
+    q / ctrl-c      quits
+
+The keys are read on their own thread, so ctrl-c interrupts the VM while it runs a command (see debugger.rs), the
+other keys typed meanwhile being dropped.
+
+The return addresses in the stack are the values right after a call instruction, which data pushed by the program could
+also look like.
+*/
+
+const RESET: &str = "\x1b[0m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const RED: &str = "\x1b[31m";
+const REVERSE: &str = "\x1b[7m";
+const DIM: &str = "\x1b[2m";
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
+const CONSOLE_LINES: usize = 1000;
+const MEMORY_HEIGHT: usize = 6;
+const MIN_SIZE: (usize, usize) = (20, 60);
+const KEYS: &str = "s step  n next  c continue  ↑↓ cursor  b breakpoint  m memory  pgup/pgdn scroll  i input  q quit";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Keys,
+    Input,
+    Address
+}
+
+// Alternate screen with a hidden cursor while the debugger runs, restored when dropped
+struct Screen;
+
+pub struct Tui {
+    debugger: Debugger,
+    console: String,
+    registers: [u16; 8],
+    disassembly_start: u16,
+    cursor: u16,
+    memory_start: usize,
+    mode: Mode,
+    line: String,
+    size: (usize, usize)
+}
+
+// Text cut or padded to the width, wrapped in the style
+fn fit(text: &str, width: usize, style: &str) -> String {
+    let text = format!("{:<width$}", text.chars().take(width).collect::<String>(), width = width);
+
+    match style {
+        "" => text,
+        _  => format!("{}{}{}", style, text, RESET)
+    }
+}
+
+fn header(title: &str, width: usize) -> String {
+    fit(&format!("─ {} {}", title, "─".repeat(width)), width, DIM)
+}
+
+impl Screen {
+    fn enter() -> Self {
+        print!("{}", ENTER_SCREEN);
+        stdout().flush().ok();
+        Self
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        print!("{}", LEAVE_SCREEN);
+        stdout().flush().ok();
+    }
+}
+
+impl Tui {
+    pub fn new(debugger: Debugger) -> Self {
+        let registers = *debugger.vm.dbg_get_registers();
+        let pc = debugger.vm.dbg_get_pc();
+
+        Self {
+            debugger,
+            console: String::new(),
+            registers,
+            disassembly_start: pc,
+            cursor: pc,
+            memory_start: 0,
+            mode: Mode::Keys,
+            line: String::new(),
+            size: (0, 0)
+        }
+    }
+
+    // Executes a line through the debugger, keeping its output in the console, until it should stop
+    pub fn execute(&mut self, line: &str) -> bool {
+        let pc = self.debugger.vm.dbg_get_pc();
+
+        self.registers = *self.debugger.vm.dbg_get_registers();
+
+        let output = self.debugger.execute(&format!("{}\n", line));
+
+        if let Some(output) = &output {
+            self.console.push_str(output);
+
+            let lines = self.console.matches('\n').count();
+
+            if lines > CONSOLE_LINES {
+                let start = self.console.match_indices('\n').nth(lines - CONSOLE_LINES).map_or(0, |(idx, _)| idx + 1);
+
+                self.console.drain(.. start);
+            }
+        }
+
+        // The cursor follows pc once it moves
+        if self.debugger.vm.dbg_get_pc() != pc {
+            self.cursor = self.debugger.vm.dbg_get_pc();
+        }
+
+        output.is_some()
+    }
+
+    // Runs until quitting or stdin is closed
+    pub fn run(&mut self) -> Result<(), String> {
+        let _raw_mode = RawMode::enable()?.restore_on_panic(LEAVE_SCREEN);
+        let _screen = Screen::enter();
+        let (sender, receiver) = mpsc::channel();
+        let keys = Rc::new(receiver);
+        let typed = Rc::clone(&keys);
+
+        thread::spawn(move || {
+            while let Some(keys) = read_keys().ok().filter(|keys| !keys.is_empty()) {
+                if keys.into_iter().any(|key| sender.send(key).is_err()) {
+                    return;
+                }
+            }
+        });
+
+        self.debugger.interrupt = Some(Box::new(move || typed.try_iter().any(|key| matches!(key, Key::Interrupt))));
+
+        loop {
+            self.draw();
+
+            let Ok(key) = keys.recv() else { break };
+
+            if !std::iter::once(key).chain(keys.try_iter()).all(|key| self.handle(key)) {
+                break;
+            }
+        }
+
+        self.debugger.interrupt = None;
+        Ok(())
+    }
+
+    // Handles the key, returning whether the debugger should go on
+    fn handle(&mut self, key: Key) -> bool {
+        match (self.mode, key) {
+            (_, Key::Interrupt)                       => return false,
+            (Mode::Keys, Key::Char('q'))              => return false,
+            (Mode::Keys, Key::Char('s'))              => return self.run_command("$ step"),
+            (Mode::Keys, Key::Char('n'))              => return self.run_command("$ next"),
+            (Mode::Keys, Key::Char('c'))              => return self.run_command("$ continue"),
+            (Mode::Keys, Key::Char('b'))              => self.toggle_breakpoint(),
+            (Mode::Keys, Key::Char('m'))              => self.mode = Mode::Address,
+            (Mode::Keys, Key::Char('i') | Key::Enter) => self.mode = Mode::Input,
+            (Mode::Keys, Key::Up)                     => self.cursor = self.previous_address(self.cursor),
+            (Mode::Keys, Key::Down)                   => self.cursor = self.next_address(self.cursor),
+            (Mode::Keys, Key::PageUp)                 => self.memory_start = self.memory_start.saturating_sub(self.memory_page()),
+            (Mode::Keys, Key::PageDown)               => self.memory_start = (self.memory_start + self.memory_page()).min(MAX_SIZE - 1),
+            (Mode::Keys, _)                           => (),
+            (_, Key::Escape)                          => {
+                self.line.clear();
+                self.mode = Mode::Keys;
+            }
+            (_, Key::Backspace)                       => { self.line.pop(); }
+            (_, Key::Char(c))                         => self.line.push(c),
+            (Mode::Input, Key::Enter)                 => {
+                let line = std::mem::take(&mut self.line);
+
+                self.mode = Mode::Keys;
+
+                // The game doesn't echo its input
+                if !line.starts_with('$') {
+                    self.console.push_str(&format!("> {}\n", line));
+                }
+
+                return self.run_command(&line);
+            }
+            (Mode::Address, Key::Enter)               => {
+                if let Ok(address) = self.line.trim().parse::<usize>() {
+                    self.memory_start = address.min(MAX_SIZE - 1);
+                }
+
+                self.line.clear();
+                self.mode = Mode::Keys;
+            }
+            _                                         => ()
+        }
+
+        true
+    }
+
+    // Executes a line which runs the VM, telling in the status line how to interrupt it meanwhile
+    fn run_command(&mut self, line: &str) -> bool {
+        print!("\x1b[1;1H{}", fit(" synacor debugger   running, ctrl-c interrupts", self.size.1, REVERSE));
+        stdout().flush().ok();
+        self.execute(line)
+    }
+
+    fn toggle_breakpoint(&mut self) {
+        let command = match self.debugger.vm.dbg_get_breakpoints().contains(&self.cursor) {
+            true  => "remove_breakpoint",
+            false => "add_breakpoint"
+        };
+
+        self.execute(&format!("$ {} {}", command, self.cursor));
+    }
+
+    fn next_address(&self, address: u16) -> u16 {
+        let next = address as usize + Instruction::decode(self.debugger.vm.dbg_get_memory(), address).size() as usize;
+
+        if next < MAX_SIZE { next as u16 } else { address }
+    }
+
+    // Closest address whose instruction ends at the given one, which may not be where the code actually starts
+    fn previous_address(&self, address: u16) -> u16 {
+        (1 ..= 4)
+            .filter_map(|size| address.checked_sub(size))
+            .find(|&previous| {
+                let instruction = Instruction::decode(self.debugger.vm.dbg_get_memory(), previous);
+
+                !matches!(instruction, Instruction::Unknown(_)) && previous + instruction.size() == address
+            })
+            .unwrap_or(address)
+    }
+
+    fn words_per_line(&self) -> usize {
+        if self.size.1 >= 7 + 16 * 6 { 16 } else { 8 }
+    }
+
+    fn memory_page(&self) -> usize {
+        (MEMORY_HEIGHT - 1) * self.words_per_line()
+    }
+
+    fn draw(&mut self) {
+        let size = terminal::size();
+
+        if size != self.size {
+            self.size = size;
+            print!("\x1b[2J");
+        }
+
+        let (rows, columns) = self.size;
+        let mut screen = vec![];
+
+        if rows < MIN_SIZE.0 || columns < MIN_SIZE.1 {
+            screen.push(fit(&format!("the terminal should be at least {} x {}", MIN_SIZE.1, MIN_SIZE.0), columns, ""));
+        } else {
+            let top = ((rows - 2 - MEMORY_HEIGHT) / 2).max(10);
+            let left = columns * 3 / 5;
+            let right = columns - left - 1;
+
+            screen.push(self.status(columns));
+            screen.extend(
+                self.disassembly(left, top)
+                    .into_iter()
+                    .zip(self.registers(right).into_iter().chain(self.stack(right, top - 6)))
+                    .map(|(left, right)| format!("{}{}│{}{}", left, DIM, RESET, right))
+            );
+            screen.extend(self.memory(columns));
+            screen.extend(self.output(columns, rows - 2 - top - MEMORY_HEIGHT));
+            screen.push(self.input_line(columns));
+        }
+
+        let frame: String = screen.iter().enumerate().map(|(row, line)| format!("\x1b[{};1H{}", row + 1, line)).collect();
+
+        print!("{}", frame);
+        stdout().flush().ok();
+    }
+
+    fn status(&self, width: usize) -> String {
+        let vm = &self.debugger.vm;
+        let state = match Instruction::decode(vm.dbg_get_memory(), vm.dbg_get_pc()) {
+            _ if vm.is_halted()     => "halted",
+            Instruction::ReadChar(_) => "waiting for input",
+            _                       => "paused"
+        };
+
+        fit(&format!(" synacor debugger   pc {}   {}   {} breakpoints", vm.dbg_get_pc(), state, vm.dbg_get_breakpoints().len()), width, REVERSE)
+    }
+
+    fn disassembly(&mut self, width: usize, height: usize) -> Vec<String> {
+        let memory = self.debugger.vm.dbg_get_memory();
+        let shown = |start: u16| Listing::new(memory).syntax(Syntax::Pseudo).lines_from(start).take(height - 1).collect::<Vec<_>>();
+        let mut lines = shown(self.disassembly_start);
+
+        // The cursor is kept in view, scrolling a line at a time when it moves just past the last one
+        if !lines.iter().any(|line| line.address == self.cursor) {
+            let last = lines.last().map(|line| line.address);
+
+            self.disassembly_start = match lines.get(1) {
+                Some(second) if last.map(|last| self.next_address(last)) == Some(self.cursor) => second.address,
+                _                                                                             => self.cursor
+            };
+            lines = shown(self.disassembly_start);
+        }
+
+        let pc = self.debugger.vm.dbg_get_pc();
+        let breakpoints = self.debugger.vm.dbg_get_breakpoints();
+
+        std::iter::once(header("disassembly", width))
+            .chain(lines.iter().map(|line| {
+                let breakpoint = breakpoints.contains(&line.address);
+                let text = format!("{}{} {}", if line.address == pc { ">" } else { " " }, if breakpoint { "*" } else { " " }, line);
+                let style = match (line.address == self.cursor, breakpoint) {
+                    (true, _)      => REVERSE,
+                    (false, true)  => RED,
+                    (false, false) => ""
+                };
+
+                fit(&text, width, style)
+            }))
+            .chain(std::iter::repeat(fit("", width, "")))
+            .take(height)
+            .collect()
+    }
+
+    fn registers(&self, width: usize) -> Vec<String> {
+        let registers = self.debugger.vm.dbg_get_registers();
+        let column = width / 2;
+        let register = |idx: usize| {
+            let style = if registers[idx] != self.registers[idx] { BOLD_YELLOW } else { "" };
+
+            fit(&format!(" r{} {:>5}", idx, registers[idx]), column, style)
+        };
+        let mut lines = vec![header("registers", width)];
+
+        lines.extend((0 .. 4).map(|idx| format!("{}{}", register(idx), register(idx + 4))));
+        lines.push(fit(&format!(" pc {:>5}", self.debugger.vm.dbg_get_pc()), width, ""));
+        lines.into_iter().map(|line| format!("{}{}", line, " ".repeat(width - 2 * column))).collect()
+    }
+
+    fn stack(&self, width: usize, height: usize) -> Vec<String> {
+        let memory = self.debugger.vm.dbg_get_memory();
+        let stack = self.debugger.vm.dbg_get_stack();
+
+        std::iter::once(header(&format!("stack ({})", stack.len()), width))
+            .chain(stack.iter().enumerate().rev().map(|(idx, &value)| {
+                let call = value.checked_sub(2).filter(|&call| matches!(Instruction::decode(memory, call), Instruction::FunctionCall(_)));
+
+                match call {
+                    Some(call) => fit(&format!(" {:>4}: {:>5}  return from the call at {}", idx, value, call), width, ""),
+                    None       => fit(&format!(" {:>4}: {:>5}", idx, value), width, "")
+                }
+            }))
+            .chain(std::iter::repeat(fit("", width, "")))
+            .take(height)
+            .collect()
+    }
+
+    fn memory(&self, width: usize) -> Vec<String> {
+        let memory = self.debugger.vm.dbg_get_memory();
+        let words_per_line = self.words_per_line();
+
+        std::iter::once(header(&format!("memory from {}", self.memory_start), width))
+            .chain((0 .. MEMORY_HEIGHT - 1).map(|row| {
+                let start = (self.memory_start + row * words_per_line).min(MAX_SIZE);
+                let words = &memory[start .. (start + words_per_line).min(MAX_SIZE)];
+                let hex: Vec<String> = words.iter().map(|word| format!("{:04x}", word)).collect();
+                let chars: String = words.iter().map(|&word| if (32 ..= 126).contains(&word) { word as u8 as char } else { '.' }).collect();
+
+                match words {
+                    [] => fit("", width, ""),
+                    _  => fit(&format!("{:>5}: {}  {}", start, hex.join(" "), chars), width, "")
+                }
+            }))
+            .collect()
+    }
+
+    fn output(&self, width: usize, height: usize) -> Vec<String> {
+        let lines: Vec<String> = self.console
+            .split('\n')
+            .flat_map(|line| {
+                let chars: Vec<char> = line.chars().collect();
+
+                match chars.len() {
+                    0 => vec![String::new()],
+                    _ => chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+                }
+            })
+            .collect();
+        let shown = &lines[lines.len().saturating_sub(height - 1) ..];
+
+        std::iter::once(header("game", width))
+            .chain(std::iter::repeat_n(fit("", width, ""), height - 1 - shown.len()))
+            .chain(shown.iter().map(|line| fit(line, width, "")))
+            .collect()
+    }
+
+    fn input_line(&self, width: usize) -> String {
+        let prompt = match self.mode {
+            Mode::Keys    => return fit(KEYS, width, DIM),
+            Mode::Input   => "> ",
+            Mode::Address => "memory address: "
+        };
+
+        // The end of the line is kept in view, followed by the cursor
+        let text = format!("{}{}", prompt, self.line);
+        let text: String = text.chars().skip((text.chars().count() + 1).saturating_sub(width)).collect();
+
+        format!("{}{} {}{}", text, REVERSE, RESET, fit("", width - text.chars().count() - 1, ""))
+    }
+}
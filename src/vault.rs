@@ -0,0 +1,423 @@
+use std::{collections::{HashMap, VecDeque}, fmt::{self, Debug, Display, Formatter}, hash::{Hash, Hasher}, ops::RangeInclusive};
+use crate::{discovery::Layout, world::{get_items, move_player, World, INVENTORY, NOWHERE}, VM};
+
+/*
+The door of the vault is controlled by a grid of rooms, each with either a number or an operator in its mosaic:
+
+    The floor of this room is a large mosaic depicting the number '9'.
+    The floor of this room is a large mosaic depicting a '-' symbol.
+
+The orb starts in the antechamber, whose pedestal has the initial weight of the orb carved on it, and the door of the
+vault (the room with an exit that isn't a compass direction) has the weight the orb should have once it gets there:
+
+    You notice the number '22' is carved into the orb's pedestal.
+    You stand before the door to the vault; it has a large '30' carved into it.
+
+The grid is read from the world starting from the location of the orb, following the compass exits to the rooms with a
+mosaic, and laid out by the directions of the exits, which are kept as the moves available from each room. Other grids
+can be given as rows of numbers and operators, where every room is connected to the adjacent ones.
+
+The operators are given to the grid as their symbols along with the function applying them, which gives nothing when
+the operation overflows (the + * - of the game by default, see OPERATORS).
+
+The solver walks the grid room by room following the rules of the game:
+
+    Operator room:  becomes the operator applied by the next number room
+    Number room:    applies the last operator (initially the + of the game) to the weight of the orb and the number
+    Bounds:         the orb evaporates (and gets back to the start) if its weight leaves the bounds (15 bits by default),
+                    which it always does if the operation overflows
+    Start room:     the orb gets back to its pedestal with its initial weight
+    End room:       the walk ends, successfully if the orb has the target weight, otherwise the orb gets back to the start
+
+Since going back to the start resets the walk, the moves that do so are never part of a shortest walk and are left out,
+like the ones leaving the bounds or reaching the end with a different weight. The walk is a BFS over the states (room,
+weight, operator), keeping every state leading to each one at the previous distance, so every shortest walk is found
+without pruning the grid, along with the weight of the orb after each step.
+
+A walk is verified on a copy of the VM with the player moved to the antechamber, taking the orb and following the
+directions: after every step the player should be in the room of the grid and still carry the orb (which otherwise got
+back to its pedestal), and once in the end room the door should open, taking the orb away.
+*/
+
+const MOSAIC: &str = "The floor of this room is a large mosaic depicting";
+const DIRECTIONS: [(&str, isize, isize); 4] = [("north", -1, 0), ("east", 0, 1), ("south", 1, 0), ("west", 0, -1)];
+const WEIGHT_BOUNDS: RangeInclusive<i32> = 0 ..= 32767;
+
+pub type Position = (usize, usize);
+
+// Operators are told apart by their symbol
+#[derive(Clone, Copy)]
+pub struct Op {
+    pub symbol: &'static str,
+    pub apply: fn(i32, i32) -> Option<i32>
+}
+
+pub const ADD: Op = Op { symbol: "+", apply: i32::checked_add };
+pub const MUL: Op = Op { symbol: "*", apply: i32::checked_mul };
+pub const SUB: Op = Op { symbol: "-", apply: i32::checked_sub };
+pub const OPERATORS: [Op; 3] = [ADD, MUL, SUB];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Number(i32),
+    Operator(Op)
+}
+
+pub struct Grid {
+    pub cells: Vec<Vec<Cell>>,
+    pub locations: Vec<Vec<usize>>,
+    pub start: Position,
+    pub end: Position,
+    pub target: i32,
+    pub exits: HashMap<Position, Vec<(&'static str, Position)>>
+}
+
+pub struct Solver<'a> {
+    grid: &'a Grid,
+    bounds: RangeInclusive<i32>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub direction: &'static str,
+    pub position: Position,
+    pub weight: i32
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
+    position: Position,
+    weight: i32,
+    op: Op
+}
+
+// Text between the first pair of single quotes
+fn quoted(text: &str) -> Option<&str> {
+    let (_, rest) = text.split_once('\'')?;
+
+    rest.split_once('\'').map(|(quoted, _)| quoted)
+}
+
+impl PartialEq for Op {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbol == other.symbol
+    }
+}
+
+impl Eq for Op {}
+
+impl Hash for Op {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.symbol.hash(state);
+    }
+}
+
+impl Debug for Op {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Op({:?})", self.symbol)
+    }
+}
+
+impl Cell {
+    pub fn parse(value: &str, operators: &[Op]) -> Result<Self, String> {
+        match operators.iter().find(|op| op.symbol == value) {
+            Some(&op) => Ok(Cell::Operator(op)),
+            None      => value.parse().map(Cell::Number).map_err(|_| {
+                let symbols: Vec<&str> = operators.iter().map(|op| op.symbol).collect();
+
+                format!("{:?} should be a number or one of {}", value, symbols.join(" "))
+            })
+        }
+    }
+}
+
+impl Grid {
+    // Grid where every room is connected to the adjacent ones
+    pub fn new(cells: Vec<Vec<Cell>>, start: Position, end: Position, target: i32) -> Result<Self, String> {
+        let rows = cells.len();
+        let columns = cells.first().map_or(0, Vec::len);
+
+        if rows == 0 || cells.iter().any(|row| row.len() != columns) {
+            return Err("the grid should have rows of the same length".to_string());
+        }
+
+        if [start, end].iter().any(|&(row, column)| row >= rows || column >= columns) {
+            return Err(format!("the start and end rooms should be within the {} x {} grid", rows, columns));
+        }
+
+        let exits = (0 .. rows)
+            .flat_map(|row| (0 .. columns).map(move |column| (row, column)))
+            .map(|(row, column)| {
+                let adjacent = DIRECTIONS
+                    .iter()
+                    .filter_map(|&(direction, rows_offset, columns_offset)| {
+                        let next_row = row.checked_add_signed(rows_offset).filter(|&next_row| next_row < rows)?;
+                        let next_column = column.checked_add_signed(columns_offset).filter(|&next_column| next_column < columns)?;
+
+                        Some((direction, (next_row, next_column)))
+                    })
+                    .collect();
+
+                ((row, column), adjacent)
+            })
+            .collect();
+        let locations = vec![vec![0; columns]; rows];
+
+        Ok(Self { cells, locations, start, end, target, exits })
+    }
+
+    // Rows of numbers and operators separated by whitespace
+    pub fn parse(text: &str, operators: &[Op], start: Position, end: Position, target: i32) -> Result<Self, String> {
+        let cells = text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.split_whitespace().map(|value| Cell::parse(value, operators)).collect::<Result<Vec<Cell>, String>>())
+            .collect::<Result<Vec<Vec<Cell>>, String>>()?;
+
+        Self::new(cells, start, end, target)
+    }
+
+    pub fn read(world: &World, operators: &[Op]) -> Result<Self, String> {
+        let orb = world.items.iter().find(|item| item.name == "orb").ok_or("no orb found")?;
+        let antechamber = world.location(orb.location).ok_or("the orb isn't in any location")?;
+        let mut positions: HashMap<usize, (isize, isize)> = HashMap::from([(antechamber.id, (0, 0))]);
+        let mut cells: HashMap<usize, Cell> = HashMap::new();
+        let mut pending = VecDeque::from([antechamber.id]);
+        let mut end = None;
+
+        while let Some(id) = pending.pop_front() {
+            let location = world.location(id).ok_or_else(|| format!("location {} not found", id))?;
+            let (row, column) = positions[&id];
+            let (text, mosaic) = location.description.split_once(MOSAIC).unwrap_or((&location.description, ""));
+            let cell = match (id == antechamber.id, mosaic) {
+                (true, _)   => quoted(text).and_then(|value| value.parse().ok()).map(Cell::Number),
+                (false, "") => continue,
+                _           => quoted(mosaic).and_then(|value| Cell::parse(value, operators).ok())
+            };
+
+            cells.insert(id, cell.ok_or_else(|| format!("the room {} has no number or operator", id))?);
+
+            for exit in &location.connections {
+                match DIRECTIONS.iter().find(|(direction, ..)| *direction == exit.label) {
+                    Some((_, rows, columns)) if !positions.contains_key(&exit.target) => {
+                        positions.insert(exit.target, (row + rows, column + columns));
+                        pending.push_back(exit.target);
+                    }
+                    Some(_)                 => (),
+                    None if !exit.synthetic => end = Some(id),
+                    None                    => ()
+                }
+            }
+        }
+
+        positions.retain(|id, _| cells.contains_key(id));
+
+        let end = end.ok_or("no room leads to the vault")?;
+        let target = quoted(&world.location(end).unwrap().description)
+            .and_then(|value| value.parse().ok())
+            .ok_or("the vault door has no weight carved")?;
+        let top = positions.values().map(|(row, _)| *row).min().unwrap();
+        let left = positions.values().map(|(_, column)| *column).min().unwrap();
+        let rows = (positions.values().map(|(row, _)| *row).max().unwrap() - top + 1) as usize;
+        let columns = (positions.values().map(|(_, column)| *column).max().unwrap() - left + 1) as usize;
+        let mut grid = Self {
+            cells: vec![vec![Cell::Number(0); columns]; rows],
+            locations: vec![vec![0; columns]; rows],
+            start: (0, 0),
+            end: (0, 0),
+            target,
+            exits: HashMap::new()
+        };
+
+        if positions.len() != rows * columns {
+            return Err(format!("the {} rooms don't fill a {} x {} grid", positions.len(), rows, columns));
+        }
+
+        let grid_position = |id: usize| -> Position { ((positions[&id].0 - top) as usize, (positions[&id].1 - left) as usize) };
+
+        for &id in positions.keys() {
+            let position = grid_position(id);
+            let exits = world.location(id).unwrap().connections
+                .iter()
+                .filter(|exit| positions.contains_key(&exit.target))
+                .filter_map(|exit| {
+                    let (direction, ..) = DIRECTIONS.iter().find(|(direction, ..)| *direction == exit.label)?;
+
+                    Some((*direction, grid_position(exit.target)))
+                })
+                .collect();
+
+            grid.exits.insert(position, exits);
+            grid.cells[position.0][position.1] = cells[&id];
+            grid.locations[position.0][position.1] = id;
+
+            if id == antechamber.id {
+                grid.start = position;
+            }
+
+            if id == end {
+                grid.end = position;
+            }
+        }
+
+        Ok(grid)
+    }
+
+    pub fn cell(&self, (row, column): Position) -> Cell {
+        self.cells[row][column]
+    }
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(grid: &'a Grid) -> Self {
+        Self { grid, bounds: WEIGHT_BOUNDS }
+    }
+
+    pub fn bounds(mut self, bounds: RangeInclusive<i32>) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    // Every shortest walk taking the orb from the start to the end room with the target weight
+    pub fn solutions(&self) -> Result<Vec<Vec<Step>>, String> {
+        let Cell::Number(weight) = self.grid.cell(self.grid.start) else { return Err("the start room should have a number".to_string()) };
+        let start = State { position: self.grid.start, weight, op: ADD };
+        let mut previous: HashMap<State, Vec<(State, &'static str)>> = HashMap::new();
+        let mut distances = HashMap::from([(start, 0)]);
+        let mut layer = vec![start];
+        let mut distance = 0;
+
+        while !layer.is_empty() {
+            let mut next_layer = vec![];
+
+            distance += 1;
+
+            for state in layer {
+                for &(direction, position) in &self.grid.exits[&state.position] {
+                    let Some(next) = self.step(state, position) else { continue };
+
+                    match distances.get(&next) {
+                        None => {
+                            distances.insert(next, distance);
+                            next_layer.push(next);
+                        }
+                        Some(&next_distance) if next_distance != distance => continue,
+                        Some(_) => ()
+                    }
+
+                    previous.entry(next).or_default().push((state, direction));
+                }
+            }
+
+            let ends: Vec<State> = next_layer.iter().copied().filter(|state| state.position == self.grid.end).collect();
+
+            if !ends.is_empty() {
+                return Ok(ends.into_iter().flat_map(|end| self.walks(&previous, start, end)).collect());
+            }
+
+            layer = next_layer;
+        }
+
+        Err(format!("no walk gets the orb to the end room weighing {}", self.grid.target))
+    }
+
+    // Walks from the start to the given state through the states previous to each one
+    fn walks(&self, previous: &HashMap<State, Vec<(State, &'static str)>>, start: State, state: State) -> Vec<Vec<Step>> {
+        if state == start {
+            return vec![vec![]];
+        }
+
+        previous[&state]
+            .iter()
+            .flat_map(|&(from, direction)| {
+                self.walks(previous, start, from).into_iter().map(move |mut walk| {
+                    walk.push(Step { direction, position: state.position, weight: state.weight });
+                    walk
+                })
+            })
+            .collect()
+    }
+
+    // State after moving into the room, if the walk can go on from it (or ends successfully in it)
+    fn step(&self, state: State, position: Position) -> Option<State> {
+        let next = match self.grid.cell(position) {
+            _ if position == self.grid.start => return None,
+            Cell::Operator(op)               => State { position, op, ..state },
+            Cell::Number(value)              => State { position, weight: (state.op.apply)(state.weight, value)?, ..state }
+        };
+
+        match position == self.grid.end {
+            _ if !self.bounds.contains(&next.weight) => None,
+            true if next.weight != self.grid.target  => None,
+            _                                        => Some(next)
+        }
+    }
+}
+
+// Follows the walk in the game, reporting the first step where it doesn't behave as the solver expects
+pub fn verify(vm: &VM, layout: &Layout, grid: &Grid, walk: &[Step]) -> Result<(), String> {
+    let mut vm = vm.clone();
+    let orb = get_items(vm.dbg_get_memory(), layout.items_table)
+        .into_iter()
+        .find(|item| item.name == "orb")
+        .ok_or("no orb found")?;
+    let (row, column) = grid.start;
+
+    move_player(&mut vm, layout.current_location, grid.locations[row][column]);
+    vm.dbg_set_output_enabled(false);
+    vm.dbg_set_output_capture(true);
+    // The game enters the antechamber (putting the orb back on its pedestal) on the next command, before taking it
+    vm.input_command("look\n");
+    vm.run();
+    vm.input_command("take orb\n");
+    vm.run();
+
+    for (idx, step) in walk.iter().enumerate() {
+        vm.dbg_take_captured_output();
+        vm.input_command(&format!("{}\n", step.direction));
+        vm.run();
+
+        let memory = vm.dbg_get_memory();
+        let location = memory[layout.current_location as usize] as usize;
+        let orb_location = memory[orb.id + 2] as usize;
+        let expected = grid.locations[step.position.0][step.position.1];
+        let last = idx + 1 == walk.len();
+        let mismatch = match () {
+            _ if location != expected               => Some(format!("the player is in {} instead of {}", location, expected)),
+            _ if last && orb_location != NOWHERE    => Some(format!("the door didn't open, the orb is in {}", orb_location)),
+            _ if !last && orb_location != INVENTORY => Some(format!("the orb is in {} instead of the inventory", orb_location)),
+            _                                       => None
+        };
+
+        if let Some(mismatch) = mismatch {
+            return Err(format!(
+                "step {} ({}) expecting the orb to weigh {}: {}\n{}",
+                idx + 1,
+                step.direction,
+                step.weight,
+                mismatch,
+                vm.dbg_take_captured_output().trim_end()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+impl Display for Cell {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Cell::Number(value) => write!(f, "{}", value),
+            Cell::Operator(op)  => write!(f, "{}", op.symbol)
+        }
+    }
+}
+
+impl Display for Grid {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for row in &self.cells {
+            writeln!(f, "{}", row.iter().map(|cell| format!("{:>3}", cell.to_string())).collect::<String>())?;
+        }
+
+        Ok(())
+    }
+}
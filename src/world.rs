@@ -0,0 +1,317 @@
+use std::collections::{HashMap, HashSet};
+use crate::VM;
+
+/*
+The game world is stored in memory as records of consecutive words:
+
+    String:   [length, char₁, ..., charₙ]
+    Location: [name ptr, description ptr, exit names ptr, exit locations ptr, enter fn]
+    Item:     [name ptr, description ptr, location, use fn]
+
+Exit names and locations are arrays prefixed by their length, just like strings, and the items table is an array of
+pointers to the item records. Items in the inventory have location 0, and the ones which aren't anywhere 32767. Strings
+are only readable once the game has decrypted them, so the memory image should be taken after the game has started.
+
+Some moves between locations happen through code instead of exits (such as the enter fn of a location or the use fn of
+an item moving the player), and are added to the world as synthetic exits.
+
+A maze is a group of connected locations sharing the same name where some exit leads back to the same location, which
+can be collapsed into its first location to simplify the world.
+*/
+
+pub const INVENTORY: usize = 0;
+pub const NOWHERE: usize = 32767;
+
+pub struct Item {
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+    pub location: usize,
+    pub use_fn: u16
+}
+
+pub struct Exit {
+    pub label: String,
+    pub target: usize,
+    pub synthetic: bool
+}
+
+pub struct Location {
+    pub id: usize,
+    pub name: String,
+    pub description: String,
+    pub connections: Vec<Exit>,
+    pub enter_fn: u16
+}
+
+pub struct World {
+    pub locations: Vec<Location>,
+    pub items: Vec<Item>
+}
+
+pub fn get_string(memory: &[u16], position: u16) -> String {
+    let position = position as usize;
+    let size = memory[position] as usize;
+
+    memory[position + 1 ..= position + size]
+        .iter()
+        .map(|v| *v as u8 as char)
+        .collect()
+}
+
+pub fn is_string(memory: &[u16], position: u16) -> bool {
+    let position = position as usize;
+
+    match memory.get(position) {
+        Some(&size) if size > 0 && position + (size as usize) < memory.len() => memory[position + 1 ..= position + size as usize]
+            .iter()
+            .all(|&c| c == 10 || (32 ..= 126).contains(&c)),
+        _ => false
+    }
+}
+
+// Whether the strings of the game are decrypted, which happens once the game starts
+pub fn is_readable(memory: &[u16], current_location: u16) -> bool {
+    memory.get(memory[current_location as usize] as usize).is_some_and(|&name| is_string(memory, name))
+}
+
+impl Item {
+    pub fn read(memory: &[u16], id: usize) -> Self {
+        Self {
+            id,
+            name: get_string(memory, memory[id]),
+            description: get_string(memory, memory[id + 1]),
+            location: memory[id + 2] as usize,
+            use_fn: memory[id + 3]
+        }
+    }
+}
+
+impl Location {
+    pub fn read(memory: &[u16], id: usize) -> Self {
+        let connection_names = memory[id + 2] as usize;
+        let connection_ids = memory[id + 3] as usize;
+
+        Self {
+            id,
+            name: get_string(memory, memory[id]),
+            description: get_string(memory, memory[id + 1]),
+            connections: (1 ..= memory[connection_names] as usize)
+                .map(|idx| Exit {
+                    label: get_string(memory, memory[connection_names + idx]),
+                    target: memory[connection_ids + idx] as usize,
+                    synthetic: false
+                })
+                .collect(),
+            enter_fn: memory[id + 4]
+        }
+    }
+}
+
+pub fn move_item(vm: &mut VM, item: usize, location: usize) {
+    vm.dbg_set_memory(item + 2, location as u16);
+}
+
+pub fn move_player(vm: &mut VM, current_location: u16, location: usize) {
+    vm.dbg_set_memory(current_location as usize, location as u16);
+}
+
+pub fn get_items(memory: &[u16], items_position: usize) -> Vec<Item> {
+    let items_size = memory[items_position] as usize;
+
+    memory[items_position + 1 ..= items_position + items_size]
+        .iter()
+        .map(|item_ptr| Item::read(memory, *item_ptr as usize))
+        .collect()
+}
+
+pub fn get_inventory(memory: &[u16], items_position: usize) -> Vec<Item> {
+    get_items(memory, items_position).into_iter().filter(|item| item.location == INVENTORY).collect()
+}
+
+// Location the current location pointer points to
+pub fn get_current_location(memory: &[u16], current_location: u16) -> Location {
+    Location::read(memory, memory[current_location as usize] as usize)
+}
+
+// Name of the place where an item is, which is either a location, the inventory or nowhere
+pub fn get_place(memory: &[u16], location: usize) -> String {
+    match location {
+        INVENTORY => "inventory".to_string(),
+        NOWHERE   => "nowhere".to_string(),
+        _         => format!("{} ({})", get_string(memory, memory[location]), location)
+    }
+}
+
+pub fn get_items_by_location(memory: &[u16], items_position: usize) -> HashMap<usize, Vec<Item>> {
+    let mut items_by_location: HashMap<usize, Vec<Item>> = HashMap::new();
+
+    for item in get_items(memory, items_position) {
+        items_by_location.entry(item.location).or_default().push(item);
+    }
+
+    items_by_location
+}
+
+// Locations reachable through exits from any of the roots, which are visited in order
+pub fn get_locations(memory: &[u16], roots: &[usize]) -> Vec<Location> {
+    let mut pending: Vec<usize> = roots.iter().rev().copied().collect();
+    let mut visited: HashSet<usize> = pending.iter().copied().collect();
+    let mut locations = vec![];
+
+    while let Some(location_id) = pending.pop() {
+        let location = Location::read(memory, location_id);
+
+        for exit in &location.connections {
+            if visited.insert(exit.target) {
+                pending.push(exit.target);
+            }
+        }
+
+        locations.push(location);
+    }
+
+    locations
+}
+
+// Only record found by name, listing the ids of the records sharing it
+fn unique<'a, T>(found: Vec<&'a T>, kind: &str, name: &str, id: impl Fn(&T) -> usize) -> Result<&'a T, String> {
+    match found[..] {
+        []       => Err(format!("no {} is {}", kind, name)),
+        [record] => Ok(record),
+        _        => Err(format!(
+            "several {}s are {}, give one of their ids: {}",
+            kind,
+            name,
+            found.iter().map(|&record| id(record).to_string()).collect::<Vec<String>>().join(", ")
+        ))
+    }
+}
+
+impl World {
+    pub fn read(memory: &[u16], items_position: usize, roots: &[usize]) -> Self {
+        Self { locations: get_locations(memory, roots), items: get_items(memory, items_position) }
+    }
+
+    pub fn location(&self, id: usize) -> Option<&Location> {
+        self.locations.iter().find(|location| location.id == id)
+    }
+
+    pub fn item(&self, id: usize) -> Option<&Item> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    // Location given by its id or its name, which should be unique (names are compared ignoring case)
+    pub fn find_location(&self, name: &str) -> Result<&Location, String> {
+        let found: Vec<&Location> = match name.parse() {
+            Ok(id) => self.location(id).into_iter().collect(),
+            Err(_) => self.locations.iter().filter(|location| location.name.eq_ignore_ascii_case(name)).collect()
+        };
+
+        unique(found, "location", name, |location| location.id)
+    }
+
+    // Items given by their id or their name (compared ignoring case)
+    pub fn items_named(&self, name: &str) -> Vec<&Item> {
+        match name.parse() {
+            Ok(id) => self.item(id).into_iter().collect(),
+            Err(_) => self.items.iter().filter(|item| item.name.eq_ignore_ascii_case(name)).collect()
+        }
+    }
+
+    // Item given by its id or its name, which should be unique
+    pub fn find_item(&self, name: &str) -> Result<&Item, String> {
+        unique(self.items_named(name), "item", name, |item| item.id)
+    }
+
+    // Synthetic exits go before the ones of the location, in the order they are added
+    pub fn add_synthetic_exit(&mut self, from: usize, label: &str, target: usize) {
+        if let Some(location) = self.locations.iter_mut().find(|location| location.id == from) {
+            let position = location.connections.iter().take_while(|exit| exit.synthetic).count();
+
+            location.connections.insert(position, Exit { label: label.to_string(), target, synthetic: true });
+        }
+    }
+
+    // Members of each maze, starting with the first one found in the locations
+    pub fn mazes(&self) -> Vec<Vec<usize>> {
+        let mut grouped: HashSet<usize> = HashSet::new();
+        let mut mazes = vec![];
+
+        for location in &self.locations {
+            if grouped.contains(&location.id) {
+                continue;
+            }
+
+            let mut group = vec![location.id];
+            let mut idx = 0;
+
+            while idx < group.len() {
+                let member = self.location(group[idx]).unwrap();
+
+                for exit in &member.connections {
+                    let same_name = self.location(exit.target).is_some_and(|next| next.name == location.name);
+
+                    if same_name && !group.contains(&exit.target) {
+                        group.push(exit.target);
+                    }
+                }
+
+                idx += 1;
+            }
+
+            grouped.extend(group.iter().copied());
+
+            let looping = group.iter().any(|&id| self.location(id).unwrap().connections.iter().any(|exit| exit.target == id));
+
+            if looping {
+                mazes.push(group);
+            }
+        }
+
+        mazes
+    }
+
+    // Replaces each maze by its first location, keeping the exits to other locations and the items in the maze
+    pub fn collapse_mazes(mut self) -> Self {
+        for maze in self.mazes() {
+            let representative = maze[0];
+            let mut exits: Vec<Exit> = vec![];
+
+            for location in self.locations.iter_mut().filter(|location| maze.contains(&location.id)) {
+                for exit in location.connections.drain(..) {
+                    let duplicated = exits.iter().any(|other| other.label == exit.label && other.target == exit.target);
+
+                    if !maze.contains(&exit.target) && !duplicated {
+                        exits.push(exit);
+                    }
+                }
+            }
+
+            self.locations.retain(|location| location.id == representative || !maze.contains(&location.id));
+
+            for location in &mut self.locations {
+                if location.id == representative {
+                    location.name = format!("{} (maze of {} locations)", location.name, maze.len());
+                    location.connections = std::mem::take(&mut exits);
+                }
+
+                for exit in &mut location.connections {
+                    if maze.contains(&exit.target) {
+                        exit.target = representative;
+                    }
+                }
+            }
+
+            for item in self.items.iter_mut().filter(|item| maze.contains(&item.location)) {
+                item.location = representative;
+            }
+        }
+
+        self
+    }
+
+    pub fn items_at(&self, location: usize) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(move |item| item.location == location)
+    }
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use synacor_vm::{discovery::discover, replay::{compare, replay}, route::Step, VM};
+
+fn check_replay(name: &str) {
+    let script = fs::read_to_string(format!("files/inputs/{}", name)).unwrap();
+    let golden = fs::read_to_string(format!("files/transcripts/{}.txt", name)).unwrap();
+    let mut vm = VM::new();
+
+    vm.load_binary("files/challenge.bin");
+
+    if let Some(difference) = compare(&golden, &replay(vm, &script)) {
+        panic!("{} {}", name, difference);
+    }
+}
+
+#[test]
+fn code_1() {
+    check_replay("code_1");
+}
+
+#[test]
+fn code_2() {
+    check_replay("code_2");
+}
+
+#[test]
+fn code_3() {
+    check_replay("code_3");
+}
+
+#[test]
+fn code_4() {
+    check_replay("code_4");
+}
+
+#[test]
+fn code_5() {
+    check_replay("code_5");
+}
+
+#[test]
+fn code_6() {
+    check_replay("code_6");
+}
+
+#[test]
+fn code_7() {
+    check_replay("code_7");
+}
+
+#[test]
+fn code_8() {
+    check_replay("code_8");
+}
+
+// The route to the ruins goes through the dark passages, which need the lantern to be lit
+#[test]
+fn planned_route() {
+    let mut vm = VM::new();
+
+    vm.load_binary("files/challenge.bin");
+    vm.dbg_set_output_enabled(false);
+
+    let layout = discover(&mut vm);
+    let world = layout.read_world(vm.dbg_get_memory());
+    let commands = layout.planner(vm.dbg_get_memory(), &world).plan(layout.start_location, &[Step::Go(2469)]).unwrap();
+
+    for command in commands {
+        vm.input_command(&format!("{}\n", command));
+        vm.run();
+    }
+
+    assert_eq!(vm.dbg_get_memory()[layout.current_location as usize], 2469);
+}